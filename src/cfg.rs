@@ -0,0 +1,259 @@
+use crate::lexer::Span;
+use crate::parser::{const_value, Block, Expr, Stmt};
+
+/// Entered at the start of the body.
+const ENTRY: usize = 0;
+/// Reached by falling off the end of the body.
+const END: usize = 1;
+/// Reached by every `return`.
+const RETURN: usize = 2;
+
+/// Control-flow graph of a function body. Nodes are program points and an
+/// edge means control may pass from one to the other.
+///
+/// Conditions that are integer constants only take the branch they select,
+/// so `while (1)` and `for (;;)` are left only through `break` or `return`.
+pub struct Cfg {
+    succs: Vec<Vec<usize>>,
+    /// The statements of each block with the node each is entered at.
+    blocks: Vec<Vec<(Span, usize)>>,
+}
+
+impl Cfg {
+    pub fn build(body: &Block) -> Cfg {
+        let mut builder = Builder {
+            cfg: Cfg {
+                succs: vec![Vec::new(); 3],
+                blocks: Vec::new(),
+            },
+            loops: Vec::new(),
+        };
+
+        let end = builder.block(&body.stmts, ENTRY);
+        builder.edge(end, END);
+
+        builder.cfg
+    }
+
+    /// Which nodes some path from the entry reaches.
+    fn reachable(&self) -> Vec<bool> {
+        let mut seen = vec![false; self.succs.len()];
+        let mut stack = vec![ENTRY];
+
+        while let Some(n) = stack.pop() {
+            if !std::mem::replace(&mut seen[n], true) {
+                stack.extend(&self.succs[n]);
+            }
+        }
+
+        seen
+    }
+
+    /// Whether some path through the body reaches its end without
+    /// returning.
+    pub fn falls_off_end(&self) -> bool {
+        self.reachable()[END]
+    }
+
+    /// The first statement of every run that cannot be reached, such as
+    /// the statements after a `return`. A block that cannot be reached
+    /// as a whole, like the body of `if (0)`, is not reported.
+    pub fn unreachable(&self) -> Vec<Span> {
+        let seen = self.reachable();
+        let mut spans = Vec::new();
+
+        for block in &self.blocks {
+            for pair in block.windows(2) {
+                let (_, prev) = pair[0];
+                let (span, n) = &pair[1];
+
+                if seen[prev] && !seen[*n] {
+                    spans.push(span.clone());
+                }
+            }
+        }
+
+        spans.sort_by_key(|span| span.start);
+        spans
+    }
+}
+
+/// Whether a condition can be true and whether it can be false. A missing
+/// condition, as in `for (;;)`, is always true.
+fn branches(cond: Option<&Expr>) -> (bool, bool) {
+    match cond.map(const_value) {
+        None => (true, false),
+        Some(Some(0)) => (false, true),
+        Some(Some(_)) => (true, false),
+        Some(None) => (true, true),
+    }
+}
+
+struct Builder {
+    cfg: Cfg,
+    /// `continue` and `break` targets of the enclosing loops, innermost last.
+    loops: Vec<(usize, usize)>,
+}
+
+impl Builder {
+    fn node(&mut self) -> usize {
+        self.cfg.succs.push(Vec::new());
+        self.cfg.succs.len() - 1
+    }
+
+    fn edge(&mut self, from: usize, to: usize) {
+        self.cfg.succs[from].push(to);
+    }
+
+    fn block(&mut self, stmts: &[Stmt], from: usize) -> usize {
+        let mut entries = Vec::new();
+        let mut n = from;
+
+        for stmt in stmts {
+            if !matches!(stmt, Stmt::Empty(_) | Stmt::Struct(_)) {
+                entries.push((stmt.span().clone(), n));
+            }
+
+            n = self.statement(stmt, n);
+        }
+
+        self.cfg.blocks.push(entries);
+        n
+    }
+
+    /// Adds `stmt`, entered at `from`, and returns the node control falls
+    /// through to after it. After a jump that node has no predecessors.
+    fn statement(&mut self, stmt: &Stmt, from: usize) -> usize {
+        match stmt {
+            Stmt::Block(b) => self.block(&b.stmts, from),
+            Stmt::If(s) => {
+                let (can_be_true, can_be_false) = branches(Some(&s.cond));
+
+                let then = self.node();
+                let els = self.node();
+                let after = self.node();
+
+                if can_be_true {
+                    self.edge(from, then);
+                }
+
+                if can_be_false {
+                    self.edge(from, els);
+                }
+
+                let then_end = self.statement(&s.then, then);
+                self.edge(then_end, after);
+
+                let els_end = match &s.els {
+                    Some(stmt) => self.statement(stmt, els),
+                    None => els,
+                };
+                self.edge(els_end, after);
+
+                after
+            }
+            Stmt::While(s) => self.loop_statement(Some(&s.cond), &s.body, from, true),
+            Stmt::For(s) => {
+                let from = match &s.init {
+                    Some(init) => self.statement(init, from),
+                    None => from,
+                };
+
+                self.loop_statement(s.cond.as_ref(), &s.body, from, true)
+            }
+            Stmt::DoWhile(s) => self.loop_statement(Some(&s.cond), &s.body, from, false),
+            Stmt::Return(_) => {
+                self.edge(from, RETURN);
+                self.node()
+            }
+            Stmt::Break(_) | Stmt::Continue(_) => {
+                // the checker rejects jumps outside loops
+                if let Some(&(test, after)) = self.loops.last() {
+                    let target = if matches!(stmt, Stmt::Break(_)) {
+                        after
+                    } else {
+                        test
+                    };
+
+                    self.edge(from, target);
+                }
+
+                self.node()
+            }
+            Stmt::Expr(_) | Stmt::Var(_) | Stmt::Struct(_) | Stmt::Empty(_) => from,
+        }
+    }
+
+    /// A loop that tests `cond` before each iteration, or after it for
+    /// `do ... while`.
+    fn loop_statement(
+        &mut self,
+        cond: Option<&Expr>,
+        body: &Stmt,
+        from: usize,
+        test_first: bool,
+    ) -> usize {
+        let (can_be_true, can_be_false) = branches(cond);
+
+        let test = self.node();
+        let start = self.node();
+        let after = self.node();
+
+        self.edge(from, if test_first { test } else { start });
+
+        if can_be_true {
+            self.edge(test, start);
+        }
+
+        if can_be_false {
+            self.edge(test, after);
+        }
+
+        self.loops.push((test, after));
+        let end = self.statement(body, start);
+        self.loops.pop();
+
+        self.edge(end, test);
+
+        after
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::Lexer;
+    use crate::parser::{Decl, Parser};
+
+    fn falls_off_end(body: &str) -> bool {
+        let src = format!("int f(int x) {{ {} }}", body);
+        let lexemes = Lexer::new("test.c", &src).lex().unwrap();
+        let (program, errors) = Parser::new(lexemes).parse();
+        assert!(errors.is_empty(), "unexpected errors: {:?}", errors);
+
+        let Some(Decl::Function(f)) = program.decls.first() else {
+            panic!("expected a function");
+        };
+
+        Cfg::build(f.body.as_ref().unwrap()).falls_off_end()
+    }
+
+    #[test]
+    fn every_path_returns() {
+        assert!(!falls_off_end("return 1;"));
+        assert!(!falls_off_end("if (x) return 1; else return 2;"));
+        assert!(!falls_off_end("while (1) { if (x) return 1; }"));
+        assert!(!falls_off_end("for (;;) { x++; }"));
+        assert!(!falls_off_end("do { return 1; } while (x);"));
+        assert!(!falls_off_end("if (0) { } return 1;"));
+    }
+
+    #[test]
+    fn some_path_falls_off() {
+        assert!(falls_off_end(""));
+        assert!(falls_off_end("if (x) return 1;"));
+        assert!(falls_off_end("while (x) return 1;"));
+        assert!(falls_off_end("while (1) { if (x) break; return 1; }"));
+        assert!(falls_off_end("for (;;) { if (x) break; }"));
+    }
+}
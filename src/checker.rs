@@ -0,0 +1,1667 @@
+use std::cell::Cell;
+use std::collections::HashMap;
+
+use crate::cfg::Cfg;
+use crate::diagnostic::Diagnostic;
+use crate::lexer::Span;
+use crate::parser::{
+    const_value, is_constant, BinaryOp, Block, Decl, Expr, ExprKind, FunctionDef, IncDecOp,
+    Initializer, Program, Stmt, StructDef, Type, UnaryOp, VarDecl,
+};
+use crate::runtime::Runtime;
+use crate::suggest;
+use crate::warning::Warning;
+
+struct Variable {
+    ty: Type,
+    is_param: bool,
+    is_const: bool,
+    span: Span,
+    /// Whether the variable has been referred to.
+    used: Cell<bool>,
+}
+
+struct Member {
+    name: String,
+    ty: Type,
+    is_const: bool,
+    span: Span,
+}
+
+struct Struct {
+    members: Vec<Member>,
+    span: Span,
+}
+
+impl Struct {
+    fn member(&self, name: &str) -> Option<&Member> {
+        self.members.iter().find(|m| m.name == name)
+    }
+}
+
+/// Names declared in one block. A function's parameters share a scope with
+/// the outermost block of its body.
+#[derive(Default)]
+struct Scope {
+    variables: HashMap<String, Variable>,
+    /// Struct names defined in the block and the definitions they name.
+    structs: HashMap<String, usize>,
+}
+
+struct Signature {
+    return_type: Type,
+    params: Vec<Type>,
+    /// Where the function was first declared.
+    declared_at: Span,
+    /// Where the body is, once one has been seen. Runtime functions count
+    /// as defined at their prototype.
+    defined_at: Option<Span>,
+    /// Declared by the runtime library rather than the program.
+    runtime: bool,
+}
+
+impl Signature {
+    fn matches(&self, other: &Signature) -> bool {
+        self.return_type == other.return_type
+            && self.params.len() == other.params.len()
+            && self
+                .params
+                .iter()
+                .zip(&other.params)
+                .all(|(a, b)| same_type(a, b))
+    }
+}
+
+/// The outermost array length does not take part in type equality; `char[]`
+/// parameters accept any `char` array, but `int m[][10]` only takes arrays
+/// of `int[10]`.
+fn same_type(a: &Type, b: &Type) -> bool {
+    match (a, b) {
+        (Type::Array(a, _), Type::Array(b, _)) => a == b,
+        _ => a == b,
+    }
+}
+
+/// Char is promoted to int in arithmetic.
+fn promote(t: &Type) -> Type {
+    match t {
+        Type::Char => Type::Int,
+        t => t.clone(),
+    }
+}
+
+/// The common type of two arithmetic operands: float if either is a float,
+/// int otherwise.
+fn arithmetic_conversion(l: &Type, r: &Type) -> Type {
+    if *l == Type::Float || *r == Type::Float {
+        Type::Float
+    } else {
+        Type::Int
+    }
+}
+
+/// Orders the arithmetic types by the values they can hold.
+fn rank(t: &Type) -> u8 {
+    match t {
+        Type::Char => 0,
+        Type::Int => 1,
+        _ => 2,
+    }
+}
+
+/// Semantic checks over a parsed program: declarations, identifier lookup
+/// and the type of every expression.
+pub struct Checker {
+    functions: HashMap<String, Signature>,
+    globals: HashMap<String, Variable>,
+    /// Struct names defined at file scope.
+    structs: HashMap<String, usize>,
+    /// Every struct definition seen so far, by the number in `Type::Struct`.
+    struct_defs: HashMap<usize, Struct>,
+    /// Block scopes of the function being checked, innermost last.
+    scopes: Vec<Scope>,
+    /// Return type of the function being checked.
+    return_type: Type,
+    /// Number of loops around the statement being checked.
+    loop_depth: usize,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Checker {
+    pub fn check(program: &Program, runtime: &Runtime) -> Vec<Diagnostic> {
+        let mut checker = Checker {
+            functions: HashMap::new(),
+            globals: HashMap::new(),
+            structs: HashMap::new(),
+            struct_defs: HashMap::new(),
+            scopes: Vec::new(),
+            return_type: Type::Void,
+            loop_depth: 0,
+            diagnostics: Vec::new(),
+        };
+
+        for f in &runtime.functions {
+            checker.declare_function(f, true);
+        }
+
+        // functions may be called before they are defined
+        for decl in &program.decls {
+            if let Decl::Function(f) = decl {
+                checker.declare_function(f, false);
+            }
+        }
+
+        for decl in &program.decls {
+            match decl {
+                Decl::Var(vars) => {
+                    for v in vars {
+                        checker.declare_global(v);
+                    }
+                }
+                Decl::Function(f) => checker.function(f),
+                Decl::Struct(def) => checker.declare_struct(def),
+            }
+        }
+
+        checker.diagnostics
+    }
+
+    fn error_count(&self) -> usize {
+        self.diagnostics.iter().filter(|d| d.is_error()).count()
+    }
+
+    fn report(&mut self, diagnostic: Diagnostic) {
+        self.diagnostics.push(diagnostic);
+    }
+
+    fn error(&mut self, code: &'static str, span: &Span, message: impl Into<String>) {
+        self.report(Diagnostic::error(span.clone(), message).with_code(code));
+    }
+
+    fn warning(&mut self, warning: Warning, span: &Span, message: impl Into<String>) {
+        self.report(Diagnostic::warning(warning, span.clone(), message));
+    }
+
+    /// Reports an error in `e`, tracing it back through the macros it was
+    /// expanded from.
+    fn expr_error(&mut self, code: &'static str, e: &Expr, message: impl Into<String>) -> Type {
+        let error = Diagnostic::error(e.span.clone(), message)
+            .with_code(code)
+            .with_expansion(e.expansion.as_ref());
+        self.report(error);
+        Type::Error
+    }
+
+    /// Reports that `e`, of type `from`, cannot be converted to `to`. Two
+    /// structs of the same name defined in different blocks are told apart
+    /// by pointing at their definitions.
+    fn conversion_error(
+        &mut self,
+        code: &'static str,
+        e: &Expr,
+        message: String,
+        from: &Type,
+        to: &Type,
+    ) -> Type {
+        let mut error = Diagnostic::error(e.span.clone(), message)
+            .with_code(code)
+            .with_expansion(e.expansion.as_ref());
+
+        let mut from = from;
+        let mut to = to;
+
+        while let (Type::Array(f, _), Type::Array(t, _)) = (from, to) {
+            from = f;
+            to = t;
+        }
+
+        if let (Type::Struct(name, f), Type::Struct(other, t)) = (from, to) {
+            let defs = (self.lookup_struct(*f), self.lookup_struct(*t));
+
+            if let (true, (Some(f), Some(t))) = (name == other, defs) {
+                error = error
+                    .with_label(f.span.clone(), "struct of the value defined here")
+                    .with_label(t.span.clone(), "expected struct defined here");
+            }
+        }
+
+        self.report(error);
+        Type::Error
+    }
+
+    /// Reports `e` as using an undeclared name, with a fix replacing the
+    /// name, which is at `name_span`, by `suggestion` if there is one. The
+    /// fix is only applied unchecked if the span is as long as the name; a
+    /// name that came from a macro has the span of the macro's.
+    fn undeclared(
+        &mut self,
+        code: &'static str,
+        e: &Expr,
+        message: impl Into<String>,
+        name: &str,
+        name_span: Span,
+        suggestion: Option<(&str, String)>,
+    ) -> Type {
+        let applicable = name_span.end.checked_sub(name_span.start) == Some(name.len());
+        let mut error = Diagnostic::error(e.span.clone(), message)
+            .with_code(code)
+            .with_expansion(e.expansion.as_ref());
+
+        if let Some((what, similar)) = suggestion {
+            error = error
+                .with_help(format!(
+                    "{} with a similar name exists: `{}`",
+                    what, similar
+                ))
+                .with_fix(name_span, similar, applicable);
+        }
+
+        self.report(error);
+        Type::Error
+    }
+
+    /// Records a prototype or definition. Later declarations of the same
+    /// function must agree with the first one, which calls are checked
+    /// against. A program may define a runtime function itself.
+    fn declare_function(&mut self, f: &FunctionDef, runtime: bool) {
+        let sig = Signature {
+            return_type: f.return_type.clone(),
+            params: f.params.iter().map(|p| p.ty.clone()).collect(),
+            declared_at: f.span.clone(),
+            defined_at: (runtime || f.body.is_some()).then(|| f.span.clone()),
+            runtime,
+        };
+
+        let Some(prev) = self.functions.get_mut(&f.name) else {
+            self.functions.insert(f.name.clone(), sig);
+            return;
+        };
+
+        if !prev.matches(&sig) {
+            let label = if prev.runtime {
+                "declared by the runtime library here"
+            } else {
+                "previous declaration here"
+            };
+
+            let error =
+                Diagnostic::error(f.span.clone(), format!("conflicting types for {}", f.name))
+                    .with_code("Q0101")
+                    .with_label(prev.declared_at.clone(), label);
+            self.report(error);
+            return;
+        }
+
+        let redefined = match (&prev.defined_at, &sig.defined_at) {
+            (Some(prev_body), Some(_)) if !prev.runtime => Some(prev_body.clone()),
+            _ => None,
+        };
+
+        if prev.defined_at.is_none() {
+            prev.defined_at = sig.defined_at;
+        }
+
+        if let Some(prev_body) = redefined {
+            let error =
+                Diagnostic::error(f.span.clone(), "function with the same name already exists")
+                    .with_code("Q0103")
+                    .with_label(prev_body, "previous definition here");
+            self.report(error);
+        }
+    }
+
+    /// The definition a struct type refers to; `None` while it is incomplete.
+    fn lookup_struct(&self, id: Option<usize>) -> Option<&Struct> {
+        id.and_then(|id| self.struct_defs.get(&id))
+    }
+
+    /// Variables, parameters and members must have a complete object type.
+    fn check_var_type(&mut self, v: &VarDecl) {
+        let elem = match &v.ty {
+            Type::Array(elem, _) => elem,
+            ty => ty,
+        };
+
+        match elem {
+            Type::Void => self.error("Q0201", &v.span, "variables cannot have type void"),
+            Type::Struct(name, id) if self.lookup_struct(*id).is_none() => {
+                let message = format!("variable has incomplete type struct {}", name);
+                self.error("Q0202", &v.span, message);
+            }
+            _ => {}
+        }
+    }
+
+    /// Defines a struct in the innermost scope, or globally outside functions.
+    /// A redefinition is reported but still checked, since the parser
+    /// resolves later uses of the name to it.
+    fn declare_struct(&mut self, def: &StructDef) {
+        let prev = match self.scopes.last() {
+            Some(scope) => scope.structs.get(&def.name),
+            None => self.structs.get(&def.name),
+        };
+        let prev = prev.and_then(|id| self.struct_defs.get(id));
+        let prev = prev.map(|s| s.span.clone());
+        let redefined = prev.is_some();
+
+        if let Some(prev) = prev {
+            let error = Diagnostic::error(def.span.clone(), "struct redefined")
+                .with_code("Q0104")
+                .with_label(prev, "previous definition here");
+            self.report(error);
+        }
+
+        let mut members: Vec<Member> = Vec::new();
+
+        for m in &def.members {
+            self.check_var_type(m);
+            self.check_array_size(m);
+
+            if let Some(init) = &m.init {
+                self.error(
+                    "Q0208",
+                    init.span(),
+                    "struct members cannot have initializers",
+                );
+            }
+
+            if let Some(prev) = members.iter().find(|member| member.name == m.name) {
+                let error = Diagnostic::error(m.span.clone(), "member redeclared")
+                    .with_code("Q0105")
+                    .with_label(prev.span.clone(), "previous declaration here");
+                self.report(error);
+                continue;
+            }
+
+            members.push(Member {
+                name: m.name.clone(),
+                ty: m.ty.clone(),
+                is_const: m.is_const,
+                span: m.span.clone(),
+            });
+        }
+
+        self.struct_defs.insert(
+            def.id,
+            Struct {
+                members,
+                span: def.span.clone(),
+            },
+        );
+
+        if !redefined {
+            let table = match self.scopes.last_mut() {
+                Some(scope) => &mut scope.structs,
+                None => &mut self.structs,
+            };
+
+            table.insert(def.name.clone(), def.id);
+        }
+    }
+
+    /// Only parameters may leave out the length of an array.
+    fn check_array_size(&mut self, v: &VarDecl) {
+        if let Type::Array(_, None) = v.ty {
+            self.error("Q0203", &v.span, "array size missing");
+        }
+    }
+
+    fn declare_global(&mut self, v: &VarDecl) {
+        self.check_var_type(v);
+        self.check_array_size(v);
+
+        if let Some(prev) = self.globals.get(&v.name) {
+            let error = Diagnostic::error(v.span.clone(), "variable redeclared")
+                .with_code("Q0102")
+                .with_label(prev.span.clone(), "previous declaration here");
+            self.report(error);
+        } else {
+            self.globals.insert(
+                v.name.clone(),
+                Variable {
+                    ty: v.ty.clone(),
+                    is_param: false,
+                    is_const: v.is_const,
+                    span: v.span.clone(),
+                    used: Cell::new(false),
+                },
+            );
+        }
+
+        if let Some(init) = &v.init {
+            let errors = self.error_count();
+            self.initializer(&v.ty, init);
+
+            // an initializer with errors is not worth a second one
+            if self.error_count() == errors {
+                self.check_constant(init);
+            }
+        }
+    }
+
+    /// Globals are initialized before `main` runs, so their initializers
+    /// cannot read variables or call functions.
+    fn check_constant(&mut self, init: &Initializer) {
+        match init {
+            Initializer::List(items, _) => {
+                for item in items {
+                    self.check_constant(item);
+                }
+            }
+            Initializer::Expr(e) if !is_constant(e) => {
+                self.expr_error("Q0209", e, "initializer element is not constant");
+            }
+            Initializer::Expr(_) => {}
+        }
+    }
+
+    /// Declares a parameter or local in the innermost scope. Hiding a name
+    /// from an enclosing scope is allowed, but warned about.
+    fn declare_local(&mut self, v: &VarDecl, is_param: bool) {
+        self.check_var_type(v);
+
+        let scope = self
+            .scopes
+            .last_mut()
+            .expect("locals are declared inside a scope");
+
+        if let Some(prev) = scope.variables.get(&v.name) {
+            let (code, message, label) = if is_param {
+                debug_assert!(prev.is_param);
+                ("Q0106", "parameter redeclared", "previous declaration here")
+            } else if prev.is_param {
+                (
+                    "Q0107",
+                    "variable cannot have the same name as a parameter",
+                    "parameter declared here",
+                )
+            } else {
+                ("Q0102", "variable redeclared", "previous declaration here")
+            };
+
+            let error = Diagnostic::error(v.span.clone(), message)
+                .with_code(code)
+                .with_label(prev.span.clone(), label);
+            self.report(error);
+            return;
+        }
+
+        scope.variables.insert(
+            v.name.clone(),
+            Variable {
+                ty: v.ty.clone(),
+                is_param,
+                is_const: v.is_const,
+                span: v.span.clone(),
+                used: Cell::new(false),
+            },
+        );
+
+        if let Some((kind, span)) = self.shadowed(&v.name) {
+            let warning = Diagnostic::warning(
+                Warning::Shadow,
+                v.span.clone(),
+                format!("declaration of {} shadows a {}", v.name, kind),
+            )
+            .with_label(span, "shadowed declaration here");
+            self.report(warning);
+        }
+    }
+
+    /// What a new declaration of `name` in the innermost scope hides, if
+    /// anything, and where that was declared.
+    fn shadowed(&self, name: &str) -> Option<(&'static str, Span)> {
+        let outer = &self.scopes[..self.scopes.len() - 1];
+
+        if let Some(v) = outer.iter().rev().find_map(|s| s.variables.get(name)) {
+            let kind = if v.is_param {
+                "parameter"
+            } else {
+                "local variable"
+            };
+
+            return Some((kind, v.span.clone()));
+        }
+
+        self.globals
+            .get(name)
+            .map(|v| ("global variable", v.span.clone()))
+    }
+
+    fn function(&mut self, f: &FunctionDef) {
+        let Some(body) = &f.body else {
+            return;
+        };
+
+        if let Type::Struct(name, id) = &f.return_type {
+            if self.lookup_struct(*id).is_none() {
+                let message = format!("return type has incomplete type struct {}", name);
+                self.error("Q0202", &f.span, message);
+            }
+        }
+
+        self.return_type = f.return_type.clone();
+        self.scopes.push(Scope::default());
+
+        for p in &f.params {
+            if p.name.is_empty() {
+                self.error("Q0108", &p.span, "parameter name omitted");
+                continue;
+            }
+
+            self.declare_local(p, true);
+        }
+
+        for stmt in &body.stmts {
+            self.statement(stmt);
+        }
+
+        self.pop_scope();
+
+        let cfg = Cfg::build(body);
+
+        for span in cfg.unreachable() {
+            self.warning(Warning::UnreachableCode, &span, "unreachable code");
+        }
+
+        // reaching the end of main returns 0
+        if f.return_type != Type::Void && f.name != "main" && cfg.falls_off_end() {
+            let message = format!(
+                "control may reach the end of non-void function {} without returning a value",
+                f.name
+            );
+            self.warning(Warning::MissingReturn, &f.span, message);
+        }
+    }
+
+    /// Leaves the innermost scope, warning about the variables in it that
+    /// were never referred to.
+    fn pop_scope(&mut self) {
+        let scope = self.scopes.pop().expect("a scope to leave");
+
+        let mut unused: Vec<(&String, &Variable)> = scope
+            .variables
+            .iter()
+            .filter(|(_, v)| !v.used.get())
+            .collect();
+        unused.sort_by_key(|(_, v)| v.span.start);
+
+        for (name, v) in unused {
+            if v.is_param {
+                let message = format!("unused parameter {}", name);
+                self.warning(Warning::UnusedParameter, &v.span, message);
+            } else {
+                let message = format!("unused variable {}", name);
+                self.warning(Warning::UnusedVariable, &v.span, message);
+            }
+        }
+    }
+
+    /// Warns about an `if`, `else` or loop whose body is a lone `;`, which
+    /// is easily left behind by mistake.
+    fn check_body(&mut self, body: &Stmt, what: &str) {
+        if let Stmt::Empty(span) = body {
+            let warning = Diagnostic::warning(
+                Warning::EmptyBody,
+                span.clone(),
+                format!("empty body in {}", what),
+            )
+            .with_help("use {} for a body that is meant to be empty");
+            self.report(warning);
+        }
+    }
+
+    /// Checks `init` against the type of the variable it initializes. Arrays
+    /// and structs take brace lists, with at most one element per array
+    /// element or member; a `char` array may also take a string literal.
+    fn initializer(&mut self, ty: &Type, init: &Initializer) {
+        let items = match init {
+            Initializer::List(items, _) => items,
+            Initializer::Expr(e) => {
+                let t = self.expr(e);
+
+                if t == Type::Error || *ty == Type::Error {
+                    return;
+                }
+
+                let is_string = matches!(e.kind, ExprKind::StrLit(_));
+
+                match (ty, &t) {
+                    // the terminating null may be dropped
+                    (Type::Array(elem, Some(len)), Type::Array(_, Some(n)))
+                        if **elem == Type::Char && is_string && n - 1 > *len =>
+                    {
+                        self.expr_error(
+                            "Q0204",
+                            e,
+                            format!("initializer string too long for {}", ty),
+                        );
+                    }
+                    (Type::Array(elem, _), _) if **elem == Type::Char && is_string => {}
+                    (Type::Array(..), _) => {
+                        self.expr_error(
+                            "Q0205",
+                            e,
+                            "array initializer must be a brace-enclosed list",
+                        );
+                    }
+                    _ if !self.implicit_conversion(e, &t, ty) => {
+                        let message = format!("cannot initialize {} with {}", ty, t);
+                        self.conversion_error("Q0206", e, message, &t, ty);
+                    }
+                    _ => {}
+                }
+
+                return;
+            }
+        };
+
+        for (i, item) in items.iter().enumerate() {
+            let elem = match ty {
+                Type::Array(elem, len) if len.is_none_or(|len| i < len) => Some((**elem).clone()),
+                Type::Array(..) => None,
+                Type::Struct(_, id) => match self.lookup_struct(*id) {
+                    Some(s) => s.members.get(i).map(|m| m.ty.clone()),
+                    None => return,
+                },
+                Type::Error => return,
+                // `int x = { 1 };` is allowed
+                _ => (i == 0).then(|| ty.clone()),
+            };
+
+            let Some(elem) = elem else {
+                self.error(
+                    "Q0207",
+                    item.span(),
+                    format!("too many initializers for {}", ty),
+                );
+                return;
+            };
+
+            self.initializer(&elem, item);
+        }
+    }
+
+    fn block(&mut self, b: &Block) {
+        self.scopes.push(Scope::default());
+
+        for stmt in &b.stmts {
+            self.statement(stmt);
+        }
+
+        self.pop_scope();
+    }
+
+    fn statement(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::Expr(e) => {
+                self.expr(e);
+            }
+            Stmt::Var(vars) => {
+                for v in vars {
+                    self.check_array_size(v);
+                    self.declare_local(v, false);
+
+                    if let Some(init) = &v.init {
+                        self.initializer(&v.ty, init);
+                    }
+                }
+            }
+            Stmt::Struct(def) => self.declare_struct(def),
+            Stmt::Block(b) => self.block(b),
+            Stmt::If(s) => {
+                self.condition(&s.cond);
+                self.check_body(&s.then, "an if statement");
+                self.statement(&s.then);
+
+                if let Some(els) = &s.els {
+                    self.check_body(els, "an else clause");
+                    self.statement(els);
+                }
+            }
+            Stmt::For(s) => {
+                // variables declared in the init clause are visible only
+                // inside the loop
+                self.scopes.push(Scope::default());
+
+                if let Some(init) = &s.init {
+                    self.statement(init);
+                }
+
+                if let Some(cond) = &s.cond {
+                    self.condition(cond);
+                }
+
+                if let Some(step) = &s.step {
+                    self.expr(step);
+                }
+
+                self.check_body(&s.body, "a for loop");
+                self.loop_body(&s.body);
+                self.pop_scope();
+            }
+            Stmt::While(s) => {
+                self.condition(&s.cond);
+                self.check_body(&s.body, "a while loop");
+                self.loop_body(&s.body);
+            }
+            Stmt::DoWhile(s) => {
+                self.loop_body(&s.body);
+                self.condition(&s.cond);
+            }
+            Stmt::Return(r) => {
+                let return_type = self.return_type.clone();
+
+                let Some(value) = &r.value else {
+                    if return_type != Type::Void {
+                        let message = format!(
+                            "return with no value in a function returning {}",
+                            return_type
+                        );
+                        self.error("Q0401", &r.span, message);
+                    }
+
+                    return;
+                };
+
+                let t = self.expr(value);
+
+                if return_type == Type::Void {
+                    if t != Type::Error {
+                        self.error(
+                            "Q0402",
+                            &r.span,
+                            "return with a value in a function returning void",
+                        );
+                    }
+                } else if !self.implicit_conversion(value, &t, &return_type) {
+                    let message = format!(
+                        "cannot return {} from a function returning {}",
+                        t, return_type
+                    );
+                    self.conversion_error("Q0403", value, message, &t, &return_type);
+                }
+            }
+            Stmt::Break(span) if self.loop_depth == 0 => {
+                self.error("Q0404", span, "break statement not within a loop");
+            }
+            Stmt::Continue(span) if self.loop_depth == 0 => {
+                self.error("Q0405", span, "continue statement not within a loop");
+            }
+            Stmt::Break(_) | Stmt::Continue(_) | Stmt::Empty(_) => {}
+        }
+    }
+
+    fn loop_body(&mut self, body: &Stmt) {
+        self.loop_depth += 1;
+        self.statement(body);
+        self.loop_depth -= 1;
+    }
+
+    /// Finds a variable from the innermost scope outward, then globally.
+    fn lookup(&self, name: &str) -> Option<&Variable> {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.variables.get(name))
+            .or_else(|| self.globals.get(name))
+    }
+
+    /// Type of `e`, reporting any error inside it, and records it on `e`.
+    /// Expressions that already had an error reported get `Type::Error`,
+    /// which is accepted everywhere so that one mistake is reported once.
+    fn expr(&mut self, e: &Expr) -> Type {
+        let ty = self.expr_type(e);
+        let _ = e.ty.set(ty.clone());
+        ty
+    }
+
+    fn expr_type(&mut self, e: &Expr) -> Type {
+        match &e.kind {
+            ExprKind::IntLit(_) => Type::Int,
+            ExprKind::RealLit(_) => Type::Float,
+            ExprKind::CharLit(_) => Type::Char,
+            ExprKind::StrLit(s) => Type::Array(Box::new(Type::Char), Some(s.len() + 1)),
+            ExprKind::Ident(name) => match self.lookup(name) {
+                Some(v) => {
+                    v.used.set(true);
+                    v.ty.clone()
+                }
+                None => {
+                    let names = self
+                        .scopes
+                        .iter()
+                        .flat_map(|scope| scope.variables.keys())
+                        .chain(self.globals.keys());
+                    let suggestion = suggest::closest(name, names.map(String::as_str))
+                        .map(|s| ("a variable", s.to_string()));
+
+                    self.undeclared(
+                        "Q0109",
+                        e,
+                        "undeclared identifier",
+                        name,
+                        e.span.clone(),
+                        suggestion,
+                    )
+                }
+            },
+            ExprKind::Unary { op, operand } => {
+                let t = if *op == UnaryOp::AddrOf {
+                    self.lvalue(operand, "take the address of", false)
+                } else {
+                    self.expr(operand)
+                };
+
+                self.unary(e, *op, t)
+            }
+            ExprKind::IncDec { op, operand } => {
+                let action = match op {
+                    IncDecOp::PreIncr | IncDecOp::PostIncr => "increment",
+                    IncDecOp::PreDecr | IncDecOp::PostDecr => "decrement",
+                };
+                let t = self.lvalue(operand, action, true);
+
+                if t == Type::Error || t.is_arithmetic() {
+                    return t;
+                }
+
+                self.expr_error(
+                    "Q0301",
+                    e,
+                    format!("invalid operand to {} (have {})", op, t),
+                )
+            }
+            ExprKind::Binary { op, lhs, rhs } => {
+                let l = self.expr(lhs);
+                let r = self.expr(rhs);
+                self.binary(e, *op, l, r)
+            }
+            ExprKind::Assign { op, lhs, rhs } => {
+                let l = self.lvalue(lhs, "assign to", true);
+                let r = self.expr(rhs);
+
+                if l == Type::Error {
+                    return Type::Error;
+                }
+
+                if let Some(op) = op {
+                    // `a op= b` is checked as `a = a op b`, but narrowing
+                    // is judged against `b` so that `c += 1` is quiet
+                    if self.binary(e, *op, l.clone(), r.clone()) == Type::Error {
+                        return Type::Error;
+                    }
+                }
+
+                if !self.implicit_conversion(rhs, &r, &l) {
+                    let message = format!("cannot assign {} to {}", r, l);
+                    return self.conversion_error("Q0303", e, message, &r, &l);
+                }
+
+                l
+            }
+            ExprKind::Ternary { cond, then, els } => {
+                self.condition(cond);
+                let errors = self.error_count();
+                let t = self.expr(then);
+                let f = self.expr(els);
+
+                // a branch with an error inside, such as `(int)(s + 1)`, may
+                // have a type but is not worth a second error
+                if t == Type::Error || f == Type::Error || self.error_count() > errors {
+                    return Type::Error;
+                }
+
+                if t.is_arithmetic() && f.is_arithmetic() {
+                    return arithmetic_conversion(&t, &f);
+                }
+
+                if !same_type(&t, &f) {
+                    return self.expr_error(
+                        "Q0305",
+                        e,
+                        format!("branches of ?: have mismatched types {} and {}", t, f),
+                    );
+                }
+
+                t
+            }
+            ExprKind::Cast { ty, expr } => {
+                let t = self.expr(expr);
+
+                // any value may be discarded with `(void)`
+                if t == Type::Error || *ty == Type::Void {
+                    return ty.clone();
+                }
+
+                if !t.is_arithmetic() || !ty.is_arithmetic() {
+                    return self.expr_error("Q0306", e, format!("cannot cast {} to {}", t, ty));
+                }
+
+                ty.clone()
+            }
+            ExprKind::Call {
+                name,
+                name_span,
+                args,
+            } => self.call(e, name, name_span, args),
+            ExprKind::Index { array, index } => {
+                let a = self.expr(array);
+                let i = self.expr(index);
+
+                if i != Type::Error && !i.is_integer() {
+                    self.expr_error(
+                        "Q0307",
+                        index,
+                        format!("array index must be an integer (have {})", i),
+                    );
+                }
+
+                if let (Type::Array(_, Some(len)), Some(i)) = (&a, const_value(index)) {
+                    if i < 0 || i >= *len as i64 {
+                        let message = format!(
+                            "index {} is out of bounds for an array of length {}",
+                            i, len
+                        );
+                        let warning =
+                            Diagnostic::warning(Warning::ArrayBounds, index.span.clone(), message)
+                                .with_expansion(index.expansion.as_ref());
+                        self.report(warning);
+                    }
+                }
+
+                match a {
+                    Type::Array(elem, _) => *elem,
+                    Type::Error => Type::Error,
+                    _ => self.expr_error("Q0308", e, "subscripted value is not an array"),
+                }
+            }
+            ExprKind::Member {
+                base,
+                member,
+                member_span,
+            } => match self.expr(base) {
+                Type::Struct(name, id) => {
+                    let def = self.lookup_struct(id);
+
+                    if let Some(m) = def.and_then(|s| s.member(member)) {
+                        return m.ty.clone();
+                    }
+
+                    let names = def.into_iter().flat_map(|s| &s.members);
+                    let suggestion = suggest::closest(member, names.map(|m| m.name.as_str()))
+                        .map(|s| ("a member", s.to_string()));
+
+                    self.undeclared(
+                        "Q0309",
+                        e,
+                        format!("struct {} has no member named {}", name, member),
+                        member,
+                        member_span.clone(),
+                        suggestion,
+                    )
+                }
+                Type::Error => Type::Error,
+                t => self.expr_error(
+                    "Q0310",
+                    e,
+                    format!("member access on {}, which is not a struct", t),
+                ),
+            },
+        }
+    }
+
+    /// Type of the operand of an assignment, `++`, `--` or `&`, which must
+    /// designate an object, and one that may be modified when `modify` is
+    /// set. Gives `Type::Error` once the operand has been reported.
+    fn lvalue(&mut self, e: &Expr, action: &str, modify: bool) -> Type {
+        if let ExprKind::Ident(name) = &e.kind {
+            if self.lookup(name).is_none() && self.functions.contains_key(name) {
+                let _ = e.ty.set(Type::Error);
+                return self.expr_error("Q0304", e, format!("cannot {} function {}", action, name));
+            }
+        }
+
+        let t = self.expr(e);
+
+        if t == Type::Error {
+            return t;
+        }
+
+        if let Some(what) = self.not_lvalue(e, modify) {
+            return self.expr_error("Q0304", e, format!("cannot {} {}", action, what));
+        }
+
+        if modify && matches!(t, Type::Array(..)) {
+            return self.expr_error(
+                "Q0304",
+                e,
+                format!("cannot {} an array of type {}", action, t),
+            );
+        }
+
+        t
+    }
+
+    /// Describes `e` if it does not designate an object, or, when `modify`
+    /// is set, designates one that is const or part of a const object.
+    fn not_lvalue(&self, e: &Expr, modify: bool) -> Option<String> {
+        match &e.kind {
+            ExprKind::Ident(name) => self
+                .lookup(name)
+                .filter(|v| modify && v.is_const)
+                .map(|_| format!("const variable {}", name)),
+            ExprKind::Index { array, .. } => self.not_lvalue(array, modify),
+            ExprKind::Member { base, member, .. } => {
+                let is_const = match base.ty.get() {
+                    Some(Type::Struct(_, id)) => self
+                        .lookup_struct(*id)
+                        .and_then(|s| s.member(member))
+                        .is_some_and(|m| m.is_const),
+                    _ => false,
+                };
+
+                if modify && is_const {
+                    return Some(format!("const member {}", member));
+                }
+
+                self.not_lvalue(base, modify)
+            }
+            ExprKind::Cast { .. } => Some("the result of a cast".to_string()),
+            ExprKind::Call { .. } => Some("the result of a function call".to_string()),
+            ExprKind::StrLit(_) => Some("a string literal".to_string()),
+            _ => Some("a value that is not an lvalue".to_string()),
+        }
+    }
+
+    /// Controlling expressions of `if`, loops, `?:`, `!`, `&&` and `||` must
+    /// have scalar type. An assignment there is likely a mistyped `==`
+    /// unless it is put in parentheses.
+    fn condition(&mut self, e: &Expr) {
+        if let ExprKind::Assign { op: None, lhs, .. } = &e.kind {
+            if e.span.start == lhs.span.start {
+                let warning = Diagnostic::warning(
+                    Warning::AssignInCondition,
+                    e.span.clone(),
+                    "assignment used as a condition",
+                )
+                .with_expansion(e.expansion.as_ref())
+                .with_help("use == to compare, or put the assignment in parentheses");
+                self.report(warning);
+            }
+        }
+
+        let t = self.expr(e);
+
+        if t != Type::Error && !t.is_arithmetic() {
+            self.expr_error(
+                "Q0311",
+                e,
+                format!("condition must have scalar type (have {})", t),
+            );
+        }
+    }
+
+    /// Whether a value of type `from`, computed by `e`, may be implicitly
+    /// converted to `to` as in an assignment, initialization, argument or
+    /// return. Arithmetic types convert freely, with a warning when the value
+    /// may not fit; anything else must match exactly.
+    fn implicit_conversion(&mut self, e: &Expr, from: &Type, to: &Type) -> bool {
+        if *from == Type::Error || *to == Type::Error {
+            return true;
+        }
+
+        if !from.is_arithmetic() || !to.is_arithmetic() {
+            return same_type(from, to);
+        }
+
+        // constants that fit are not worth a warning, as in `char c = 65;`
+        let fits = *to == Type::Char
+            && const_value(e).is_some_and(|v| (i8::MIN as i64..=u8::MAX as i64).contains(&v));
+
+        if rank(to) < rank(from) && !fits {
+            let warning = Diagnostic::warning(
+                Warning::ImplicitNarrowing,
+                e.span.clone(),
+                format!(
+                    "implicit conversion from {} to {} may change its value",
+                    from, to
+                ),
+            )
+            .with_expansion(e.expansion.as_ref())
+            .with_help(format!("cast to {} to make the conversion explicit", to));
+            self.report(warning);
+        }
+
+        true
+    }
+
+    fn unary(&mut self, e: &Expr, op: UnaryOp, t: Type) -> Type {
+        if t == Type::Error {
+            return Type::Error;
+        }
+
+        let ok = match op {
+            UnaryOp::Neg | UnaryOp::Plus | UnaryOp::Not => t.is_arithmetic(),
+            UnaryOp::BitNot => t.is_integer(),
+            UnaryOp::AddrOf | UnaryOp::Deref => {
+                return self.expr_error("Q0302", e, format!("unary {} is not supported", op));
+            }
+        };
+
+        if !ok {
+            return self.expr_error(
+                "Q0301",
+                e,
+                format!("invalid operand to unary {} (have {})", op, t),
+            );
+        }
+
+        match op {
+            UnaryOp::Not => Type::Int,
+            _ => promote(&t),
+        }
+    }
+
+    fn binary(&mut self, e: &Expr, op: BinaryOp, l: Type, r: Type) -> Type {
+        if l == Type::Error || r == Type::Error {
+            return Type::Error;
+        }
+
+        let ok = if op.is_integer_only() {
+            l.is_integer() && r.is_integer()
+        } else {
+            l.is_arithmetic() && r.is_arithmetic()
+        };
+
+        if !ok {
+            let kind = if op.is_integer_only() {
+                "integer"
+            } else {
+                "arithmetic"
+            };
+
+            return self.expr_error(
+                "Q0301",
+                e,
+                format!(
+                    "operands of {} must have {} type (have {} and {})",
+                    op, kind, l, r
+                ),
+            );
+        }
+
+        match op {
+            BinaryOp::LogOr
+            | BinaryOp::LogAnd
+            | BinaryOp::Eq
+            | BinaryOp::Ne
+            | BinaryOp::Lt
+            | BinaryOp::Le
+            | BinaryOp::Gt
+            | BinaryOp::Ge => Type::Int,
+            // the result of a shift has the promoted type of its left operand
+            BinaryOp::Shl | BinaryOp::Shr => promote(&l),
+            _ => arithmetic_conversion(&l, &r),
+        }
+    }
+
+    fn call(&mut self, e: &Expr, name: &str, name_span: &Span, args: &[Expr]) -> Type {
+        let arg_types: Vec<Type> = args.iter().map(|a| self.expr(a)).collect();
+
+        let Some(sig) = self.functions.get(name) else {
+            let names = self.functions.keys().map(String::as_str);
+            let suggestion = suggest::closest(name, names).map(|s| ("a function", s.to_string()));
+
+            return self.undeclared(
+                "Q0110",
+                e,
+                "undeclared function",
+                name,
+                name_span.clone(),
+                suggestion,
+            );
+        };
+
+        let return_type = sig.return_type.clone();
+        let params = sig.params.clone();
+
+        if params.len() != args.len() {
+            let message = format!(
+                "function {} expects {} arguments, but {} given",
+                name,
+                params.len(),
+                args.len()
+            );
+            return self.expr_error("Q0312", e, message);
+        }
+
+        for (i, (p, a)) in params.iter().zip(&arg_types).enumerate() {
+            if !self.implicit_conversion(&args[i], a, p) {
+                let message = format!(
+                    "argument {} of {} has type {}, expected {}",
+                    i + 1,
+                    name,
+                    a,
+                    p
+                );
+                self.conversion_error("Q0313", &args[i], message, a, p);
+            }
+        }
+
+        return_type
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::{Lexeme, Lexer};
+    use crate::parser::Parser;
+    use crate::preprocessor::Preprocessor;
+
+    fn check_lexemes(lexemes: Vec<Lexeme>) -> Vec<Diagnostic> {
+        let (program, errors) = Parser::new(lexemes).parse();
+        assert!(errors.is_empty(), "unexpected syntax errors: {:?}", errors);
+
+        Checker::check(&program, &Runtime::bundled())
+    }
+
+    fn diagnostics(src: &str) -> Vec<Diagnostic> {
+        check_lexemes(Lexer::new("test.c", src).lex().expect("input lexes"))
+    }
+
+    /// Messages of the errors in `src`.
+    fn check(src: &str) -> Vec<String> {
+        diagnostics(src)
+            .into_iter()
+            .filter(|d| d.is_error())
+            .map(|d| d.message)
+            .collect()
+    }
+
+    /// Checks `src` after running it through the preprocessor, which only
+    /// reads files.
+    fn check_preprocessed(name: &str, src: &str) -> Vec<Diagnostic> {
+        let path = std::env::temp_dir().join(format!("quark-{}-{}.c", std::process::id(), name));
+        std::fs::write(&path, src).unwrap();
+        let lexemes = Preprocessor::new().preprocess_file(path.to_str().unwrap());
+        std::fs::remove_file(&path).unwrap();
+
+        check_lexemes(lexemes.expect("input preprocesses"))
+    }
+
+    #[test]
+    fn valid_program() {
+        let src = "int total;\n\
+                   int add(int a, int b) { return a + b; }\n\
+                   int main() { int x; x = add(1, 2) << 2; x ^= 3; total += x; return x; }";
+
+        assert_eq!(check(src), Vec::<String>::new());
+    }
+
+    #[test]
+    fn integer_only_operators() {
+        let src = "int main() { float f; int i; f = f << 1; i = i ^ 1.5; f %= 2.0; return 0; }";
+
+        assert_eq!(
+            check(src),
+            [
+                "operands of << must have integer type (have float and int)",
+                "operands of ^ must have integer type (have int and float)",
+                "operands of % must have integer type (have float and float)",
+            ]
+        );
+    }
+
+    #[test]
+    fn structs() {
+        let src = "struct P { int x; float y; };\n\
+                   struct P p;\n\
+                   struct Q q;\n\
+                   struct P { int z; };\n\
+                   int main() { p.x = 1; p.y = 2.0; p.z = 3; p.x.y = 4; return 0; }";
+
+        assert_eq!(
+            check(src),
+            [
+                "variable has incomplete type struct Q",
+                "struct redefined",
+                "struct P has no member named z",
+                "member access on int, which is not a struct",
+            ]
+        );
+    }
+
+    #[test]
+    fn structs_with_the_same_name_are_different_types() {
+        let src = "struct P { int x; };\n\
+                   struct P g;\n\
+                   int main() { struct P { int x; }; struct P l; l = g; return 0; }";
+
+        assert_eq!(check(src), ["cannot assign struct P to struct P"]);
+    }
+
+    #[test]
+    fn mismatched_structs_point_at_their_definitions() {
+        let src = "struct P { int a; int b; };\n\
+                   int sum(struct P p) { return p.a + p.b; }\n\
+                   int main() {\n\
+                       struct P { int x; };\n\
+                       struct P q;\n\
+                       q.x = 1;\n\
+                       return sum(q);\n\
+                   }";
+        let diagnostics = diagnostics(src);
+
+        assert_eq!(diagnostics.len(), 1);
+        let lines: Vec<usize> = diagnostics[0]
+            .labels
+            .iter()
+            .map(|l| l.span.lineno)
+            .collect();
+        assert_eq!(lines, [4, 1]);
+    }
+
+    #[test]
+    fn undeclared_names_suggest_a_fix() {
+        let diagnostics = diagnostics("int main() { int count = 0; return conut; }");
+        let d = &diagnostics[0];
+
+        assert_eq!(d.code, Some("Q0109"));
+        assert_eq!(d.fixes[0].replacement, "count");
+        assert!(d.fixes[0].applicable);
+    }
+
+    #[test]
+    fn member_from_a_macro_is_not_fixed() {
+        let diagnostics = check_preprocessed(
+            "member",
+            "struct P { int x; };\n\
+             #define GET(s) s.xxxxxxxxxx\n\
+             int main() { struct P p;\n\
+             GET(p);\n\
+             return 0; }",
+        );
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].code, Some("Q0309"));
+        assert!(diagnostics[0].fixes.iter().all(|f| !f.applicable));
+    }
+
+    #[test]
+    fn errors_in_macros_point_at_the_definition() {
+        let diagnostics = check_preprocessed(
+            "expansion",
+            "#define TWICE(x) ((x) + (x))\nint main() { return TWICE(\"a\"); }",
+        );
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].span.lineno, 2);
+        assert_eq!(diagnostics[0].labels[0].span.lineno, 1);
+    }
+
+    #[test]
+    fn prototypes() {
+        let src = "int twice(int x);\n\
+                   int main() { return twice(2); }\n\
+                   int twice(int x) { return x * 2; }\n\
+                   int putchar(int c) { return c; }";
+
+        assert_eq!(check(src), Vec::<String>::new());
+
+        let src = "int f(int x);\n\
+                   float f(int x) { return 1.0; }\n\
+                   int g() { return 0; }\n\
+                   int g() { return 1; }\n\
+                   void putint(float x) { }";
+
+        assert_eq!(
+            check(src),
+            [
+                "conflicting types for f",
+                "function with the same name already exists",
+                "conflicting types for putint",
+            ]
+        );
+    }
+
+    #[test]
+    fn initializer_lists() {
+        let src = "struct P { int x; float y; };\n\
+                   int a[3] = {1, 2, 3};\n\
+                   struct P p = {1, 2.5};\n\
+                   char s[3] = \"hi\";\n\
+                   int b[2] = {1, 2, 3};\n\
+                   struct P q = {1, 2.5, 3};\n\
+                   int c = {1, 2};\n\
+                   char t[2] = \"hi!\";";
+
+        assert_eq!(
+            check(src),
+            [
+                "too many initializers for int[2]",
+                "too many initializers for struct P",
+                "too many initializers for int",
+                "initializer string too long for char[2]",
+            ]
+        );
+    }
+
+    #[test]
+    fn large_array_with_a_short_initializer() {
+        let src = "int a[200000000] = {1};\nint main() { int b[300000000] = {1, 2}; return b[1]; }";
+
+        assert_eq!(check(src), Vec::<String>::new());
+    }
+
+    #[test]
+    fn global_initializers_must_be_constant() {
+        let src = "int one() { return 1; }\n\
+                   int a = 1;\n\
+                   int b = a;\n\
+                   int c = one();\n\
+                   int d[2] = {1, a + 1};\n\
+                   float e = (float)1 / 2.0;\n\
+                   int f = x;";
+
+        assert_eq!(
+            check(src),
+            [
+                "initializer element is not constant",
+                "initializer element is not constant",
+                "initializer element is not constant",
+                "undeclared identifier",
+            ]
+        );
+    }
+
+    #[test]
+    fn constant_index_out_of_bounds() {
+        let lexemes = Lexer::new(
+            "test.c",
+            "int m[2][3];\nint main() { m[1][2] = 0; m[2][0] = 1; m[0][-1] = 2; return 0; }",
+        )
+        .lex()
+        .unwrap();
+        let (program, _) = Parser::new(lexemes).parse();
+        let warnings: Vec<String> = Checker::check(&program, &Runtime::bundled())
+            .into_iter()
+            .filter(|e| !e.is_error())
+            .map(|e| e.message)
+            .collect();
+
+        assert_eq!(
+            warnings,
+            [
+                "index 2 is out of bounds for an array of length 2",
+                "index -1 is out of bounds for an array of length 3",
+            ]
+        );
+    }
+
+    #[test]
+    fn block_scopes() {
+        let src = "int g;\n\
+                   int f(int p) {\n\
+                       { int x; struct S { int a; }; struct S s; x = s.a; }\n\
+                       { float x; struct S { float b; }; struct S s; x = s.b; }\n\
+                       { int g; int y; { int y; } }\n\
+                       { int p; }\n\
+                       return x;\n\
+                   }";
+
+        let shadowed: Vec<String> = diagnostics(src)
+            .into_iter()
+            .filter(|d| d.warning == Some(Warning::Shadow))
+            .map(|d| d.message)
+            .collect();
+
+        assert_eq!(
+            shadowed,
+            [
+                "declaration of g shadows a global variable",
+                "declaration of y shadows a local variable",
+                "declaration of p shadows a parameter",
+            ]
+        );
+        assert_eq!(check(src), ["undeclared identifier"]);
+    }
+
+    #[test]
+    fn arithmetic_conversions() {
+        let src = "float f;\n\
+                   int main() { char c; c = 'a'; f = c + 1; f = 2 * f; return (int)f % 3; }";
+        let lexemes = Lexer::new("test.c", src).lex().unwrap();
+        let (program, _) = Parser::new(lexemes).parse();
+
+        assert_eq!(Checker::check(&program, &Runtime::bundled()).len(), 0);
+
+        let Decl::Function(main) = &program.decls[1] else {
+            panic!("expected a function");
+        };
+        let types: Vec<String> = main.body.as_ref().unwrap().stmts[2..4]
+            .iter()
+            .map(|s| match s {
+                Stmt::Expr(Expr {
+                    kind: ExprKind::Assign { rhs, .. },
+                    ..
+                }) => rhs.ty.get().unwrap().to_string(),
+                s => panic!("expected an assignment, found {:?}", s),
+            })
+            .collect();
+
+        assert_eq!(types, ["int", "float"]);
+    }
+
+    #[test]
+    fn one_error_for_a_bad_branch() {
+        let src = "struct S { int a; };\n\
+                   int f(int x);\n\
+                   int main() {\n\
+                       struct S s;\n\
+                       int x = 1 ? (int)(s + 1) : s;\n\
+                       int y = 1 ? f(\"a\" + 1) : s;\n\
+                       int z = 1 ? 2 : s;\n\
+                       return x + y + z;\n\
+                   }";
+
+        assert_eq!(
+            check(src),
+            [
+                "operands of + must have arithmetic type (have struct S and int)",
+                "operands of + must have arithmetic type (have char[2] and int)",
+                "branches of ?: have mismatched types int and struct S",
+            ]
+        );
+    }
+
+    #[test]
+    fn returns_and_loops() {
+        let src = "int f(int x) { if (x) return 1; }\n\
+                   void g() { return 1; }\n\
+                   int h() { return; }\n\
+                   int k() { while (1) { } }\n\
+                   int main() { break; continue; while (1) { break; } return 2; }";
+
+        let missing: Vec<String> = diagnostics(src)
+            .into_iter()
+            .filter(|d| d.warning == Some(Warning::MissingReturn))
+            .map(|d| d.message)
+            .collect();
+
+        assert_eq!(
+            missing,
+            ["control may reach the end of non-void function f without returning a value"]
+        );
+        assert_eq!(
+            check(src),
+            [
+                "return with a value in a function returning void",
+                "return with no value in a function returning int",
+                "break statement not within a loop",
+                "continue statement not within a loop",
+            ]
+        );
+    }
+
+    #[test]
+    fn lvalues() {
+        let src = "struct P { const int id; int n; };\n\
+                   const int limit = 3;\n\
+                   int f() { return 0; }\n\
+                   int main() {\n\
+                       struct P p; int a[2]; int x;\n\
+                       x = 1; a[0] = 2; p.n = 3; x++;\n\
+                       limit = 4; p.id = 5; f() = 6; 7 = x; (int)x = 8; a = a; f = 0; --limit;\n\
+                       return x;\n\
+                   }";
+
+        assert_eq!(
+            check(src),
+            [
+                "cannot assign to const variable limit",
+                "cannot assign to const member id",
+                "cannot assign to the result of a function call",
+                "cannot assign to a value that is not an lvalue",
+                "cannot assign to the result of a cast",
+                "cannot assign to an array of type int[2]",
+                "cannot assign to function f",
+                "cannot decrement const variable limit",
+            ]
+        );
+    }
+
+    #[test]
+    fn undeclared_names() {
+        assert_eq!(
+            check("int main() { return x + f(); }"),
+            ["undeclared identifier", "undeclared function"]
+        );
+    }
+
+    #[test]
+    fn warnings() {
+        let diagnostics =
+            diagnostics("int main() { int unused; int x = 3.5; if (x = 1) {} return x; }");
+        let warnings: Vec<Warning> = diagnostics.iter().filter_map(|d| d.warning).collect();
+
+        assert!(warnings.contains(&Warning::UnusedVariable));
+        assert!(warnings.contains(&Warning::ImplicitNarrowing));
+        assert!(warnings.contains(&Warning::AssignInCondition));
+    }
+
+    #[test]
+    fn diagnostics_have_codes() {
+        let diagnostics = diagnostics(
+            "struct S { int a; };\n\
+             int main() { struct S s; int x; int y = s + 1; x = 2.5; return z; }",
+        );
+        let codes: Vec<Option<&str>> = diagnostics.iter().map(|d| d.code).collect();
+
+        assert_eq!(
+            codes,
+            [Some("Q0301"), Some("Q0904"), Some("Q0109"), Some("Q0901")]
+        );
+    }
+}
@@ -0,0 +1,471 @@
+use std::collections::HashMap;
+use std::fs;
+use std::rc::Rc;
+
+use crate::lexer::{Expansion, Span};
+use crate::suggest;
+use crate::warning::Warning;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// A secondary span worth pointing at, such as an earlier declaration.
+#[derive(Clone, Debug)]
+pub struct Label {
+    pub span: Span,
+    pub message: String,
+}
+
+/// A suggested edit to the input: `replacement` in place of the text `span`
+/// covers, which for an insertion is empty.
+#[derive(Clone, Debug)]
+pub struct Fix {
+    pub span: Span,
+    pub replacement: String,
+    /// Whether the edit is certain enough to make without a person
+    /// checking it, as `--apply-fixes` does.
+    pub applicable: bool,
+}
+
+impl Fix {
+    pub fn description(&self) -> String {
+        if self.span.start == self.span.end {
+            format!("insert `{}`", self.replacement)
+        } else {
+            format!("replace with `{}`", self.replacement)
+        }
+    }
+}
+
+/// `text` with `fixes` made to it, and how many were made. A fix is left
+/// out if it overlaps one already made, or if it replaces anything but an
+/// identifier close to its replacement, which happens when the span is that
+/// of a macro the misspelt name was expanded from.
+pub fn apply_fixes(text: &str, fixes: &[Fix]) -> (String, usize) {
+    let mut fixes: Vec<&Fix> = fixes.iter().collect();
+    fixes.sort_by_key(|f| (f.span.start, f.span.end));
+    fixes.dedup_by(|a, b| a.span == b.span && a.replacement == b.replacement);
+
+    let mut out = String::with_capacity(text.len());
+    let mut pos = 0;
+    let mut applied = 0;
+
+    for f in fixes {
+        let Some(old) = text.get(f.span.start..f.span.end) else {
+            continue;
+        };
+
+        let misspelt = old.is_empty() || suggest::closest(old, [f.replacement.as_str()]).is_some();
+
+        if f.span.start < pos || !misspelt {
+            continue;
+        }
+
+        out += &text[pos..f.span.start];
+        out += &f.replacement;
+        pos = f.span.end;
+        applied += 1;
+    }
+
+    out += &text[pos..];
+    (out, applied)
+}
+
+/// A problem found in the input by any phase of the compiler.
+#[derive(Clone, Debug)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// Stable identifier of the kind of problem.
+    pub code: Option<&'static str>,
+    /// The warning this is, which stays set if it is made into an error.
+    pub warning: Option<Warning>,
+    pub message: String,
+    /// Where the problem is.
+    pub span: Span,
+    pub labels: Vec<Label>,
+    pub notes: Vec<String>,
+    pub help: Option<String>,
+    pub fixes: Vec<Fix>,
+}
+
+impl Diagnostic {
+    pub fn error(span: Span, message: impl Into<String>) -> Self {
+        Self::new(Severity::Error, span, message)
+    }
+
+    pub fn warning(warning: Warning, span: Span, message: impl Into<String>) -> Self {
+        Diagnostic {
+            code: Some(warning.code()),
+            warning: Some(warning),
+            ..Self::new(Severity::Warning, span, message)
+        }
+    }
+
+    fn new(severity: Severity, span: Span, message: impl Into<String>) -> Self {
+        Diagnostic {
+            severity,
+            code: None,
+            warning: None,
+            message: message.into(),
+            span,
+            labels: Vec::new(),
+            notes: Vec::new(),
+            help: None,
+            fixes: Vec::new(),
+        }
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+
+    pub fn with_code(mut self, code: &'static str) -> Self {
+        self.code = Some(code);
+        self
+    }
+
+    pub fn with_label(mut self, span: Span, message: impl Into<String>) -> Self {
+        self.labels.push(Label {
+            span,
+            message: message.into(),
+        });
+        self
+    }
+
+    pub fn with_note(mut self, note: impl Into<String>) -> Self {
+        self.notes.push(note.into());
+        self
+    }
+
+    pub fn with_help(mut self, help: impl Into<String>) -> Self {
+        self.help = Some(help.into());
+        self
+    }
+
+    pub fn with_fix(
+        mut self,
+        span: Span,
+        replacement: impl Into<String>,
+        applicable: bool,
+    ) -> Self {
+        self.fixes.push(Fix {
+            span,
+            replacement: replacement.into(),
+            applicable,
+        });
+        self
+    }
+
+    /// Points at the definition of every macro the primary span was
+    /// expanded from, innermost first.
+    pub fn with_expansion(mut self, expansion: Option<&Rc<Expansion>>) -> Self {
+        for e in expansion.iter().flat_map(|e| e.backtrace()) {
+            self = self.with_label(
+                e.defined_at.clone(),
+                format!("in expansion of macro {}, defined here", e.name),
+            );
+        }
+
+        self
+    }
+}
+
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+const RED: &str = "\x1b[1;31m";
+const YELLOW: &str = "\x1b[1;33m";
+const BLUE: &str = "\x1b[1;34m";
+
+/// Formats diagnostics for a terminal:
+///
+/// ```text
+/// error: variable redeclared
+///  --> a.c:4:9
+///   |
+/// 3 |     int x;
+///   |         - previous declaration here
+/// 4 |     int x;
+///   |         ^
+/// ```
+///
+/// Source lines are read from the files the spans name, or from text
+/// registered with `add_source`.
+pub struct Renderer {
+    color: bool,
+    sources: HashMap<String, Option<String>>,
+}
+
+/// One underlined span within a snippet.
+struct Mark<'a> {
+    span: &'a Span,
+    message: &'a str,
+    primary: bool,
+    /// Color of the underline.
+    style: &'static str,
+}
+
+impl Renderer {
+    pub fn new(color: bool) -> Self {
+        Renderer {
+            color,
+            sources: HashMap::new(),
+        }
+    }
+
+    /// Makes `text` the contents of the file called `name`, for sources
+    /// that are not on disk.
+    pub fn add_source(&mut self, name: &str, text: &str) {
+        self.sources
+            .insert(name.to_string(), Some(text.to_string()));
+    }
+
+    fn paint(&self, text: &str, style: &str) -> String {
+        if self.color {
+            format!("{}{}{}", style, text, RESET)
+        } else {
+            text.to_string()
+        }
+    }
+
+    fn line(&mut self, file: &str, lineno: usize) -> Option<String> {
+        let source = self
+            .sources
+            .entry(file.to_string())
+            .or_insert_with(|| fs::read_to_string(file).ok());
+
+        let line = source.as_deref()?.lines().nth(lineno.checked_sub(1)?)?;
+        Some(line.to_string())
+    }
+
+    pub fn render(&mut self, d: &Diagnostic) -> String {
+        let (name, style) = match d.severity {
+            Severity::Error => ("error", RED),
+            Severity::Warning => ("warning", YELLOW),
+        };
+
+        let title = match d.code {
+            Some(code) => format!("{}[{}]", name, code),
+            None => name.to_string(),
+        };
+
+        let flag = match d.warning {
+            Some(w) if d.is_error() => format!(" [-Werror={}]", w.name()),
+            Some(w) => format!(" [-W{}]", w.name()),
+            None => String::new(),
+        };
+
+        let mut out = format!(
+            "{}{}{}\n",
+            self.paint(&title, style),
+            self.paint(&format!(": {}", d.message), BOLD),
+            flag
+        );
+
+        let mut marks = vec![Mark {
+            span: &d.span,
+            message: "",
+            primary: true,
+            style,
+        }];
+        marks.extend(d.labels.iter().map(|l| Mark {
+            span: &l.span,
+            message: &l.message,
+            primary: false,
+            style: BLUE,
+        }));
+
+        let width = marks
+            .iter()
+            .map(|m| m.span.lineno.to_string().len())
+            .max()
+            .unwrap_or(1);
+        let pad = " ".repeat(width);
+
+        // marks are grouped by file, the primary span's file first
+        let mut files: Vec<&str> = Vec::new();
+
+        for m in &marks {
+            if !files.contains(&&*m.span.infile_name) {
+                files.push(&m.span.infile_name);
+            }
+        }
+
+        let mut notes = Vec::new();
+
+        for (i, file) in files.into_iter().enumerate() {
+            let mut in_file: Vec<&Mark> = marks
+                .iter()
+                .filter(|m| &*m.span.infile_name == file)
+                .collect();
+            in_file.sort_by_key(|m| (m.span.lineno, m.span.col));
+
+            // the primary span is the one the location refers to
+            let at = if i == 0 { &d.span } else { in_file[0].span };
+            let arrow = if i == 0 { "-->" } else { ":::" };
+            let location = if at.lineno == 0 {
+                file.to_string()
+            } else {
+                format!("{}:{}:{}", file, at.lineno, at.col)
+            };
+            out += &format!("{}{} {}\n", pad, self.paint(arrow, BLUE), location);
+
+            let gutter = self.paint("|", BLUE);
+            out += &format!("{} {}\n", pad, gutter);
+
+            let mut prev_line = None;
+
+            for m in in_file {
+                let lineno = m.span.lineno;
+
+                let Some(text) = self.line(file, lineno) else {
+                    if !m.primary {
+                        notes.push(format!(
+                            "{} at {}:{}",
+                            m.message, m.span.infile_name, m.span.lineno
+                        ));
+                    }
+                    continue;
+                };
+
+                if prev_line != Some(lineno) {
+                    if prev_line.is_some_and(|prev| lineno > prev + 1) {
+                        out += &format!("{}\n", self.paint("...", BLUE));
+                    }
+
+                    let number = self.paint(&format!("{:>width$} |", lineno), BLUE);
+                    out += &format!("{} {}\n", number, text);
+                    prev_line = Some(lineno);
+                }
+
+                out += &format!("{} {} {}\n", pad, gutter, self.underline(m, &text));
+            }
+        }
+
+        for note in notes.iter().chain(&d.notes) {
+            out += &format!("{} {} note: {}\n", pad, self.paint("=", BLUE), note);
+        }
+
+        if let Some(help) = &d.help {
+            out += &format!("{} {} help: {}\n", pad, self.paint("=", BLUE), help);
+        }
+
+        out
+    }
+
+    /// The caret line under `text` for `m`. Tabs before the span are kept so
+    /// that the carets line up; a span running past the end of its first
+    /// line is underlined to the end of that line.
+    fn underline(&self, m: &Mark, text: &str) -> String {
+        let start = (m.span.col - 1).min(text.len());
+        let indent: String = text
+            .bytes()
+            .take(start)
+            .map(|b| if b == b'\t' { '\t' } else { ' ' })
+            .collect();
+
+        let len = if m.span.end_lineno == m.span.lineno {
+            m.span.end_col.saturating_sub(m.span.col)
+        } else {
+            text.len().saturating_sub(start)
+        };
+
+        let marker = if m.primary { "^" } else { "-" };
+        let marker = marker.repeat(len.max(1));
+
+        let mut line = format!("{}{}", indent, self.paint(&marker, m.style));
+
+        if !m.message.is_empty() {
+            line += &format!(" {}", self.paint(m.message, m.style));
+        }
+
+        line
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Span of bytes `start..end` of the first line of test.c.
+    fn span(start: usize, end: usize) -> Span {
+        Span {
+            infile_name: "test.c".into(),
+            lineno: 1,
+            col: start + 1,
+            end_lineno: 1,
+            end_col: end + 1,
+            start,
+            end,
+        }
+    }
+
+    fn fix(start: usize, end: usize, replacement: &str) -> Fix {
+        Fix {
+            span: span(start, end),
+            replacement: replacement.to_string(),
+            applicable: true,
+        }
+    }
+
+    #[test]
+    fn fixes_are_applied() {
+        let text = "x = conut\n";
+        let (fixed, applied) = apply_fixes(text, &[fix(9, 9, ";"), fix(4, 9, "count")]);
+
+        assert_eq!(fixed, "x = count;\n");
+        assert_eq!(applied, 2);
+    }
+
+    #[test]
+    fn unlikely_fixes_are_skipped() {
+        let text = "x = V\n";
+        let fixes = [
+            fix(4, 5, "count"),
+            fix(4, 5, "W"),
+            fix(4, 5, "U"),
+            fix(0, 20, "y"),
+        ];
+        let (fixed, applied) = apply_fixes(text, &fixes);
+
+        // `V` is too short to be a misspelling of `count`, `U` overlaps `W`
+        // and the last fix is past the end of the text
+        assert_eq!(fixed, "x = W\n");
+        assert_eq!(applied, 1);
+    }
+
+    #[test]
+    fn fix_descriptions() {
+        assert_eq!(fix(3, 3, ";").description(), "insert `;`");
+        assert_eq!(fix(3, 5, "ab").description(), "replace with `ab`");
+    }
+
+    #[test]
+    fn render() {
+        let mut renderer = Renderer::new(false);
+        renderer.add_source("test.c", "int x;\nint x;\n");
+
+        let mut second = span(4, 5);
+        second.lineno = 2;
+        second.end_lineno = 2;
+
+        let d = Diagnostic::error(second, "variable redeclared")
+            .with_code("Q0102")
+            .with_label(span(4, 5), "previous declaration here")
+            .with_help("rename one of them");
+
+        assert_eq!(
+            renderer.render(&d),
+            "error[Q0102]: variable redeclared\n \
+             --> test.c:2:5\n  \
+              |\n\
+             1 | int x;\n  \
+              |     - previous declaration here\n\
+             2 | int x;\n  \
+              |     ^\n  \
+              = help: rename one of them\n"
+        );
+    }
+}
@@ -0,0 +1,1418 @@
+/// Long-form descriptions of every diagnostic code, each with an example
+/// that triggers it and a corrected version, for `--explain`.
+const EXPLANATIONS: &[(&str, &str)] = &[
+    (
+        "Q0001",
+        r#"A source file could not be read.
+
+The input file, or a file named by #include, does not exist or cannot be
+opened. Included files are looked up relative to the file that includes
+them.
+
+Erroneous code example:
+
+    #include "missing.h"
+
+Check the file name and its location:
+
+    #include "util.h"
+"#,
+    ),
+    (
+        "Q0002",
+        r#"A block comment is never closed.
+
+Every /* must be matched by a */ later in the same file; comments do not
+nest.
+
+Erroneous code example:
+
+    /* the counter
+    int count;
+
+Close the comment where it ends:
+
+    /* the counter */
+    int count;
+"#,
+    ),
+    (
+        "Q0003",
+        r#"A character that cannot start any token was found.
+
+Characters such as @, $ and ` have no meaning in the language, and may only
+appear inside string and character literals or comments.
+
+Erroneous code example:
+
+    int total$ = 0;
+
+Use only letters, digits and underscores in names:
+
+    int total_ = 0;
+"#,
+    ),
+    (
+        "Q0004",
+        r#"A numeric literal is malformed.
+
+Integer literals are decimal, octal (with a leading 0, digits 0-7) or
+hexadecimal (with a leading 0x and at least one hex digit). Real literals
+need digits before or after the decimal point. Values must fit in the
+literal's type.
+
+Erroneous code example:
+
+    int mask = 0x;
+    int mode = 089;
+
+Write a complete literal in one base:
+
+    int mask = 0xff;
+    int mode = 89;
+"#,
+    ),
+    (
+        "Q0005",
+        r#"A string or character literal is not closed on the line it starts.
+
+Literals cannot span lines. Use \n for a newline inside a string.
+
+Erroneous code example:
+
+    putstring("hello
+    ");
+
+Close the literal on the same line:
+
+    putstring("hello\n");
+"#,
+    ),
+    (
+        "Q0006",
+        r#"A character literal does not hold exactly one character.
+
+'' is empty, and 'ab' holds two characters. Use a string literal for text
+longer than one character.
+
+Erroneous code example:
+
+    char c = 'ab';
+
+Hold one character, or an escape sequence for one:
+
+    char c = 'a';
+    char nl = '\n';
+"#,
+    ),
+    (
+        "Q0007",
+        r#"An escape sequence in a string or character literal is not valid.
+
+The recognized escapes are \n \t \r \a \b \f \v \\ \' \" \?, octal escapes
+of one to three digits such as \0 and \101, and hex escapes such as \x41.
+The value of an escape must fit in a char.
+
+Erroneous code example:
+
+    char c = '\q';
+    char d = '\x';
+
+Use a recognized escape:
+
+    char c = 'q';
+    char d = '\x41';
+"#,
+    ),
+    (
+        "Q0010",
+        r#"A line starting with # is not a known preprocessing directive.
+
+The supported directives are #include, #define, #undef, #ifdef, #ifndef,
+#else, #endif and #pragma.
+
+Erroneous code example:
+
+    #if DEBUG
+    #endif
+
+Use a supported directive:
+
+    #ifdef DEBUG
+    #endif
+"#,
+    ),
+    (
+        "Q0011",
+        r#"Conditional directives are not balanced.
+
+Every #ifdef or #ifndef must be closed by an #endif in the same file, with
+at most one #else between them.
+
+Erroneous code example:
+
+    #ifdef DEBUG
+    int verbose = 1;
+
+Close the conditional:
+
+    #ifdef DEBUG
+    int verbose = 1;
+    #endif
+"#,
+    ),
+    (
+        "Q0012",
+        r#"A #define, #undef or #ifdef is malformed.
+
+A macro name must be an identifier, followed by nothing else for #undef and
+#ifdef. Parameters of a function-like macro are distinct identifiers in
+parentheses immediately after the name. In the body, # must be followed by
+a parameter, and ## cannot come first or last.
+
+Erroneous code example:
+
+    #define MAX(a, a) a
+    #define JOIN(a, b) ## a b
+
+Write a well-formed definition:
+
+    #define MAX(a, b) ((a) > (b) ? (a) : (b))
+    #define JOIN(a, b) a ## b
+"#,
+    ),
+    (
+        "Q0013",
+        r#"A function-like macro is used incorrectly.
+
+The macro must be given as many arguments as it has parameters, and the
+argument list must be closed. Pasting tokens together with ## must give a
+single valid token.
+
+Erroneous code example:
+
+    #define MAX(a, b) ((a) > (b) ? (a) : (b))
+    int m = MAX(1);
+
+Pass every argument:
+
+    #define MAX(a, b) ((a) > (b) ? (a) : (b))
+    int m = MAX(1, 2);
+"#,
+    ),
+    (
+        "Q0014",
+        r#"An #include directive is malformed.
+
+Only the quoted form #include "file" is supported; there are no system
+headers to find with <file>.
+
+Erroneous code example:
+
+    #include <util.h>
+
+Quote the file name:
+
+    #include "util.h"
+"#,
+    ),
+    (
+        "Q0015",
+        r#"Files include each other without end.
+
+A file may not include itself, directly or through other files, and
+includes may not nest too deeply. Protect headers with an include guard.
+
+Erroneous code example:
+
+    /* a.h */
+    #include "b.h"
+
+    /* b.h */
+    #include "a.h"
+
+Guard the headers so that each is read once:
+
+    /* a.h */
+    #ifndef A_H
+    #define A_H
+    #include "b.h"
+    #endif
+"#,
+    ),
+    (
+        "Q0016",
+        r#"A #pragma quark directive is malformed.
+
+The only quark pragma is suppress, followed by the names of the warnings to
+silence on the next line. Pragmas for other tools are ignored.
+
+Erroneous code example:
+
+    #pragma quark suppress unused
+    int scratch;
+
+Name the warning as -W does:
+
+    #pragma quark suppress unused-variable
+    int scratch;
+"#,
+    ),
+    (
+        "Q0101",
+        r#"A function is declared with conflicting types.
+
+Every declaration of a function, including the runtime library's, must have
+the same return type and parameter types.
+
+Erroneous code example:
+
+    int scale(int x);
+    float scale(float x) { return x * 2.0; }
+
+Make the declarations agree:
+
+    float scale(float x);
+    float scale(float x) { return x * 2.0; }
+"#,
+    ),
+    (
+        "Q0102",
+        r#"A variable was declared twice in the same scope.
+
+Each name may be declared only once per block, and once among the globals.
+A declaration in an inner block may hide an outer one (see Q0903).
+
+Erroneous code example:
+
+    int main() {
+        int count = 0;
+        int count = 1;
+        return count;
+    }
+
+Declare the variable once, or give the second one another name:
+
+    int main() {
+        int count = 0;
+        count = 1;
+        return count;
+    }
+"#,
+    ),
+    (
+        "Q0103",
+        r#"A function is defined more than once.
+
+A function may be declared any number of times but has only one body.
+Runtime library functions may be defined once by the program.
+
+Erroneous code example:
+
+    int twice(int x) { return 2 * x; }
+    int twice(int x) { return x + x; }
+
+Keep one definition:
+
+    int twice(int x) { return 2 * x; }
+"#,
+    ),
+    (
+        "Q0104",
+        r#"A struct is defined twice in the same scope.
+
+Erroneous code example:
+
+    struct point { int x; int y; };
+    struct point { float x; float y; };
+
+Give each struct its own name:
+
+    struct point { int x; int y; };
+    struct fpoint { float x; float y; };
+"#,
+    ),
+    (
+        "Q0105",
+        r#"A struct has two members with the same name.
+
+Erroneous code example:
+
+    struct point { int x; int x; };
+
+Name every member differently:
+
+    struct point { int x; int y; };
+"#,
+    ),
+    (
+        "Q0106",
+        r#"A function has two parameters with the same name.
+
+Erroneous code example:
+
+    int add(int a, int a) { return a + a; }
+
+Name every parameter differently:
+
+    int add(int a, int b) { return a + b; }
+"#,
+    ),
+    (
+        "Q0107",
+        r#"A variable in the outermost block of a function has the name of one of
+its parameters.
+
+Parameters share a scope with the function body, so this declares the name
+twice. Declare it in an inner block to hide the parameter on purpose.
+
+Erroneous code example:
+
+    int f(int n) {
+        int n = 0;
+        return n;
+    }
+
+Use another name:
+
+    int f(int n) {
+        int m = n;
+        return m;
+    }
+"#,
+    ),
+    (
+        "Q0108",
+        r#"A parameter of a function definition has no name.
+
+Prototypes may leave parameter names out, but a function body can only use
+named parameters.
+
+Erroneous code example:
+
+    int square(int) { return 0; }
+
+Name the parameter:
+
+    int square(int x) { return x * x; }
+"#,
+    ),
+    (
+        "Q0109",
+        r#"A name is used that has not been declared.
+
+Variables must be declared before they are used, in the current block, an
+enclosing block or globally.
+
+Erroneous code example:
+
+    int main() {
+        total = 1;
+        return 0;
+    }
+
+Declare the variable first:
+
+    int main() {
+        int total;
+        total = 1;
+        return 0;
+    }
+"#,
+    ),
+    (
+        "Q0110",
+        r#"A function is called that has not been declared.
+
+Functions may be called before their definition, but must be declared or
+defined somewhere in the file, or by the runtime library.
+
+Erroneous code example:
+
+    int main() {
+        printint(1);
+        return 0;
+    }
+
+Call a declared function:
+
+    int main() {
+        putint(1);
+        return 0;
+    }
+"#,
+    ),
+    (
+        "Q0201",
+        r#"A variable, parameter or member is declared with type void.
+
+void only describes the absence of a value, as the return type of a
+function; no object can hold one.
+
+Erroneous code example:
+
+    void result;
+
+Give the variable a type with values:
+
+    int result;
+"#,
+    ),
+    (
+        "Q0202",
+        r#"A struct type is used that has not been defined.
+
+A struct must be defined, in the current scope or an enclosing one, before
+variables, members or return values of that type are declared.
+
+Erroneous code example:
+
+    struct point origin;
+    struct point { int x; int y; };
+
+Define the struct first:
+
+    struct point { int x; int y; };
+    struct point origin;
+"#,
+    ),
+    (
+        "Q0203",
+        r#"An array is declared without a length.
+
+Only array parameters may leave their first length out; other arrays need
+every length.
+
+Erroneous code example:
+
+    int values[];
+
+Give the length:
+
+    int values[10];
+"#,
+    ),
+    (
+        "Q0204",
+        r#"A string literal is too long for the char array it initializes.
+
+The array must hold every character of the string. The terminating null
+may be dropped when the characters fill the array exactly.
+
+Erroneous code example:
+
+    char name[3] = "quark";
+
+Make the array long enough:
+
+    char name[6] = "quark";
+"#,
+    ),
+    (
+        "Q0205",
+        r#"An array is initialized with a single value.
+
+Arrays take a brace-enclosed list of elements; only char arrays may also
+take a string literal.
+
+Erroneous code example:
+
+    int zeros[3] = 0;
+
+Use a list:
+
+    int zeros[3] = { 0, 0, 0 };
+"#,
+    ),
+    (
+        "Q0206",
+        r#"An initializer has a type that cannot be converted to the variable's
+type.
+
+Arithmetic types convert to each other; structs and arrays must match
+exactly.
+
+Erroneous code example:
+
+    struct point { int x; int y; };
+    struct point p;
+    int n = p;
+
+Initialize with a value of a compatible type:
+
+    struct point { int x; int y; };
+    struct point p;
+    int n = p.x;
+"#,
+    ),
+    (
+        "Q0207",
+        r#"A brace-enclosed initializer has more elements than the array or
+struct it initializes.
+
+Erroneous code example:
+
+    int pair[2] = { 1, 2, 3 };
+
+Drop the extra elements, or make the array longer:
+
+    int pair[2] = { 1, 2 };
+"#,
+    ),
+    (
+        "Q0208",
+        r#"A struct member is given an initializer.
+
+Members get their values when a variable of the struct type is
+initialized or assigned, not in the struct definition.
+
+Erroneous code example:
+
+    struct counter { int count = 0; };
+
+Initialize the variable instead:
+
+    struct counter { int count; };
+    struct counter c = { 0 };
+"#,
+    ),
+    (
+        "Q0209",
+        r#"A global variable is initialized with an expression that is not
+constant.
+
+Globals are initialized before `main` runs, so their initializers may
+only combine literals with operators. Reading a variable, calling a
+function or assigning is not allowed.
+
+Erroneous code example:
+
+    int width = 8;
+    int area = width * width;
+
+Use a constant, or assign the value at the start of `main`:
+
+    int width = 8;
+    int area = 8 * 8;
+"#,
+    ),
+    (
+        "Q0301",
+        r#"An operator is applied to an operand of the wrong type.
+
+Arithmetic and comparison operators need int, char or float operands; %,
+the bitwise operators and shifts need integer operands. Structs and arrays
+cannot be operands.
+
+Erroneous code example:
+
+    float x = 7.5;
+    int r = x % 2;
+
+Use operands of a suitable type:
+
+    float x = 7.5;
+    int r = (int)x % 2;
+"#,
+    ),
+    (
+        "Q0302",
+        r#"The unary & or * operator is used.
+
+The language has no pointers, so addresses cannot be taken or followed.
+
+Erroneous code example:
+
+    int x;
+    int y = *x;
+
+Use the value directly:
+
+    int x;
+    int y = x;
+"#,
+    ),
+    (
+        "Q0303",
+        r#"A value is assigned to a variable whose type it cannot be converted to.
+
+Erroneous code example:
+
+    struct point { int x; int y; };
+    struct point p;
+    int n;
+    n = p;
+
+Assign a value of a compatible type:
+
+    struct point { int x; int y; };
+    struct point p;
+    int n;
+    n = p.y;
+"#,
+    ),
+    (
+        "Q0304",
+        r#"An expression is assigned to, incremented, decremented or has its
+address taken, but it does not designate an object that may be modified.
+
+Only variables, array elements and struct members can be modified, and not
+if they are const. Whole arrays cannot be assigned.
+
+Erroneous code example:
+
+    const int limit = 10;
+    limit = 20;
+
+Modify a variable that is not const:
+
+    int limit = 10;
+    limit = 20;
+"#,
+    ),
+    (
+        "Q0305",
+        r#"The two branches of ?: have types that cannot be combined.
+
+Both branches must be arithmetic, or have the same type.
+
+Erroneous code example:
+
+    struct point { int x; int y; };
+    struct point p;
+    int n = 1 ? p : 0;
+
+Make the branches agree:
+
+    struct point { int x; int y; };
+    struct point p;
+    int n = 1 ? p.x : 0;
+"#,
+    ),
+    (
+        "Q0306",
+        r#"A cast converts between types that cannot be converted.
+
+Casts convert between arithmetic types, or discard a value with (void).
+
+Erroneous code example:
+
+    struct point { int x; int y; };
+    struct point p;
+    int n = (int)p;
+
+Cast an arithmetic value:
+
+    struct point { int x; int y; };
+    struct point p;
+    int n = (int)p.x;
+"#,
+    ),
+    (
+        "Q0307",
+        r#"An array is indexed with a value that is not an integer.
+
+Erroneous code example:
+
+    int values[4];
+    int v = values[1.0];
+
+Index with an integer:
+
+    int values[4];
+    int v = values[1];
+"#,
+    ),
+    (
+        "Q0308",
+        r#"Something that is not an array is indexed.
+
+Erroneous code example:
+
+    int count;
+    int c = count[0];
+
+Only index arrays:
+
+    int counts[4];
+    int c = counts[0];
+"#,
+    ),
+    (
+        "Q0309",
+        r#"A struct is accessed through a member it does not have.
+
+Erroneous code example:
+
+    struct point { int x; int y; };
+    struct point p;
+    int z = p.z;
+
+Use a member of the struct:
+
+    struct point { int x; int y; };
+    struct point p;
+    int x = p.x;
+"#,
+    ),
+    (
+        "Q0310",
+        r#"The . operator is applied to a value that is not a struct.
+
+Erroneous code example:
+
+    int n;
+    int x = n.x;
+
+Access members of structs only:
+
+    struct point { int x; int y; };
+    struct point n;
+    int x = n.x;
+"#,
+    ),
+    (
+        "Q0311",
+        r#"A condition does not have a scalar type.
+
+The conditions of if, while, do and for, the first operand of ?:, and the
+operands of !, && and || must be int, char or float.
+
+Erroneous code example:
+
+    struct point { int x; int y; };
+    struct point p;
+    if (p) putint(1);
+
+Test a scalar value:
+
+    struct point { int x; int y; };
+    struct point p;
+    if (p.x) putint(1);
+"#,
+    ),
+    (
+        "Q0312",
+        r#"A function is called with the wrong number of arguments.
+
+Erroneous code example:
+
+    int add(int a, int b) { return a + b; }
+    int main() { return add(1); }
+
+Pass one argument per parameter:
+
+    int add(int a, int b) { return a + b; }
+    int main() { return add(1, 2); }
+"#,
+    ),
+    (
+        "Q0313",
+        r#"An argument has a type that cannot be converted to its parameter's
+type.
+
+Arithmetic arguments convert to arithmetic parameters. Arrays must have the
+parameter's element type, and structs must be of the same struct.
+
+Erroneous code example:
+
+    int sum(int v[], int n);
+    float data[4];
+    int total() { return sum(data, 4); }
+
+Pass an array of the right element type:
+
+    int sum(int v[], int n);
+    int data[4];
+    int total() { return sum(data, 4); }
+"#,
+    ),
+    (
+        "Q0401",
+        r#"A return statement has no value in a function that returns one.
+
+Erroneous code example:
+
+    int first(int v[]) {
+        return;
+    }
+
+Return a value of the function's return type:
+
+    int first(int v[]) {
+        return v[0];
+    }
+"#,
+    ),
+    (
+        "Q0402",
+        r#"A return statement has a value in a function returning void.
+
+Erroneous code example:
+
+    void show(int x) {
+        return putint(x);
+    }
+
+Return without a value:
+
+    void show(int x) {
+        putint(x);
+        return;
+    }
+"#,
+    ),
+    (
+        "Q0403",
+        r#"A returned value cannot be converted to the function's return type.
+
+Erroneous code example:
+
+    struct point { int x; int y; };
+    int getx(struct point p) {
+        return p;
+    }
+
+Return a value of a compatible type:
+
+    struct point { int x; int y; };
+    int getx(struct point p) {
+        return p.x;
+    }
+"#,
+    ),
+    (
+        "Q0404",
+        r#"A break statement is not inside a loop.
+
+break leaves the innermost while, do or for loop, so it has to be inside
+one.
+
+Erroneous code example:
+
+    int main() {
+        break;
+    }
+
+Use return to leave a function:
+
+    int main() {
+        return 0;
+    }
+"#,
+    ),
+    (
+        "Q0405",
+        r#"A continue statement is not inside a loop.
+
+continue starts the next iteration of the innermost while, do or for loop,
+so it has to be inside one.
+
+Erroneous code example:
+
+    int main() {
+        continue;
+    }
+
+Only continue inside a loop:
+
+    int main() {
+        int i;
+        for (i = 0; i < 10; i++) {
+            if (i % 2) continue;
+            putint(i);
+        }
+        return 0;
+    }
+"#,
+    ),
+    (
+        "Q0501",
+        r#"A syntax error: the next token is not one that can appear there.
+
+The message names what was expected, such as a ';' to end a statement or a
+')' to close a parenthesis. The error is often caused by something missing
+just before the reported position.
+
+Erroneous code example:
+
+    int main() {
+        int x = 1
+        return x;
+    }
+
+Add the missing token:
+
+    int main() {
+        int x = 1;
+        return x;
+    }
+"#,
+    ),
+    (
+        "Q0502",
+        r#"A struct is defined inside another struct.
+
+Define the inner struct first and use it as a member type.
+
+Erroneous code example:
+
+    struct line {
+        struct point { int x; int y; } start;
+    };
+
+Define the structs separately:
+
+    struct point { int x; int y; };
+    struct line { struct point start; struct point end; };
+"#,
+    ),
+    (
+        "Q0503",
+        r#"An array declaration leaves out a length other than the first.
+
+Only the first length of an array parameter may be empty; the others are
+needed to find the elements.
+
+Erroneous code example:
+
+    int trace(int m[3][]);
+
+Give every length but the first:
+
+    int trace(int m[][3]);
+"#,
+    ),
+    (
+        "Q0504",
+        r#"An array length is not a positive integer constant.
+
+Array lengths must be known before the program runs, so they may only use
+literals and operators, not variables or function calls.
+
+Erroneous code example:
+
+    int n = 4;
+    int values[n];
+
+Use a constant, or a macro for one:
+
+    #define N 4
+    int values[N];
+"#,
+    ),
+    (
+        "Q0601",
+        r#"A runtime header given with --runtime contains something other than
+function prototypes.
+
+Runtime headers only describe the functions the runtime library provides;
+they cannot define functions, variables or structs.
+
+Erroneous code example:
+
+    /* io.h */
+    int buffer_size;
+    int readint();
+
+Only declare functions:
+
+    /* io.h */
+    int readint();
+"#,
+    ),
+    (
+        "Q0701",
+        r#"A program run with `quark run` divided an integer by zero.
+
+Integer division and remainder by zero have no defined result in C, so the
+interpreter stops the program. Float division by zero gives an infinity
+instead.
+
+Erroneous code example:
+
+    int main() {
+        int n = getint();
+        putint(100 / n);
+        return 0;
+    }
+
+Check the divisor first:
+
+    int main() {
+        int n = getint();
+        if (n != 0)
+            putint(100 / n);
+        return 0;
+    }
+"#,
+    ),
+    (
+        "Q0702",
+        r#"A program run with `quark run` indexed an array out of bounds.
+
+An array of length n has elements 0 to n - 1. Constant indices outside that
+range are warned about when compiling (-Warray-bounds); others are caught
+when the program runs.
+
+Erroneous code example:
+
+    int main() {
+        int a[3];
+        int i;
+        for (i = 0; i <= 3; i++)
+            a[i] = i;
+        return 0;
+    }
+
+Stop before the length:
+
+    int main() {
+        int a[3];
+        int i;
+        for (i = 0; i < 3; i++)
+            a[i] = i;
+        return 0;
+    }
+"#,
+    ),
+    (
+        "Q0703",
+        r#"A program run with `quark run` called a function that has no definition.
+
+A prototype is enough to compile a call, but running it needs a body. Of
+the runtime library, only the functions in the bundled header are built
+into the interpreter; those declared in --runtime headers are not.
+
+Erroneous code example:
+
+    int square(int x);
+
+    int main() {
+        return square(3);
+    }
+
+Define the function:
+
+    int square(int x) {
+        return x * x;
+    }
+
+    int main() {
+        return square(3);
+    }
+"#,
+    ),
+    (
+        "Q0704",
+        r#"A program run with `quark run` recursed too deeply.
+
+The interpreter allows 10000 calls to be active at once, and stops a
+program that goes deeper, which is usually recursion that never reaches
+its base case.
+
+Erroneous code example:
+
+    int count(int n) {
+        return count(n - 1) + 1;
+    }
+
+    int main() {
+        return count(5);
+    }
+
+Stop the recursion:
+
+    int count(int n) {
+        if (n == 0)
+            return 0;
+        return count(n - 1) + 1;
+    }
+
+    int main() {
+        return count(5);
+    }
+"#,
+    ),
+    (
+        "Q0705",
+        r#"A program run with `quark run` declares `main` with parameters or a
+return type other than `int`.
+
+The interpreter calls `main` with no arguments, and the value it returns
+is the program's exit status. Other phases accept any `main`.
+
+Erroneous code example:
+
+    float main(int argc) {
+        return 0;
+    }
+
+Declare `main` as `int main()` or `int main(void)`:
+
+    int main(void) {
+        return 0;
+    }
+"#,
+    ),
+    (
+        "Q0901",
+        r#"A local variable is declared but never used (-Wunused-variable).
+
+The variable may be left over from earlier code, or a different variable
+may be used by mistake.
+
+Erroneous code example:
+
+    int main() {
+        int scratch;
+        return 0;
+    }
+
+Remove the variable, or silence the warning for one line with:
+
+    #pragma quark suppress unused-variable
+"#,
+    ),
+    (
+        "Q0902",
+        r#"A function parameter is never used (-Wunused-parameter).
+
+This warning is off by default; enable it with -Wunused-parameter.
+
+Erroneous code example:
+
+    int zero(int x) {
+        return 0;
+    }
+
+Use the parameter, or remove it:
+
+    int zero() {
+        return 0;
+    }
+"#,
+    ),
+    (
+        "Q0903",
+        r#"A declaration hides a variable or parameter of the same name from an
+enclosing scope (-Wshadow).
+
+Uses of the name inside the block refer to the new declaration, which is
+easily confused with the outer one.
+
+Erroneous code example:
+
+    int count;
+    void reset() {
+        int count = 0;
+    }
+
+Use a distinct name, or refer to the outer variable:
+
+    int count;
+    void reset() {
+        count = 0;
+    }
+"#,
+    ),
+    (
+        "Q0904",
+        r#"A value is implicitly converted to a type that may not hold it
+(-Wimplicit-narrowing).
+
+Converting float to int drops the fraction, and int to char keeps only the
+low byte. Constants that fit in the target type are not reported.
+
+Erroneous code example:
+
+    float ratio = 2.5;
+    int whole = ratio;
+
+Cast to make the conversion explicit:
+
+    float ratio = 2.5;
+    int whole = (int)ratio;
+"#,
+    ),
+    (
+        "Q0905",
+        r#"A statement can never be executed (-Wunreachable-code).
+
+It follows a return, break or continue, or a loop that is never left.
+
+Erroneous code example:
+
+    int f(int x) {
+        return x;
+        x = x + 1;
+    }
+
+Remove the statement, or move it before the jump:
+
+    int f(int x) {
+        x = x + 1;
+        return x;
+    }
+"#,
+    ),
+    (
+        "Q0906",
+        r#"An if, else, while or for has a lone ; as its body (-Wempty-body).
+
+A stray semicolon after the condition ends the statement, so the block
+that follows runs unconditionally.
+
+Erroneous code example:
+
+    int x = getint();
+    if (x > 0);
+    {
+        putint(x);
+    }
+
+Remove the semicolon, or write {} for a body that is meant to be empty:
+
+    int x = getint();
+    if (x > 0) {
+        putint(x);
+    }
+"#,
+    ),
+    (
+        "Q0907",
+        r#"An assignment is used as a condition (-Wassign-in-condition).
+
+This is usually a mistyped comparison with ==.
+
+Erroneous code example:
+
+    int x = getint();
+    if (x = 0)
+        putint(x);
+
+Compare with ==, or parenthesize an assignment that is intended:
+
+    int x = getint();
+    int c;
+    if (x == 0)
+        putint(x);
+    while ((c = getchar()))
+        putchar(c);
+"#,
+    ),
+    (
+        "Q0908",
+        r#"Control can reach the end of a function that returns a value without a
+return statement (-Wmissing-return).
+
+The value the caller receives is then undefined. main is exempt, as
+reaching its end returns 0.
+
+Erroneous code example:
+
+    int sign(int x) {
+        if (x > 0) return 1;
+        if (x < 0) return -1;
+    }
+
+Return on every path:
+
+    int sign(int x) {
+        if (x > 0) return 1;
+        if (x < 0) return -1;
+        return 0;
+    }
+"#,
+    ),
+    (
+        "Q0909",
+        r#"An array is indexed with a constant outside its bounds
+(-Warray-bounds).
+
+An array of length n has elements 0 to n - 1.
+
+Erroneous code example:
+
+    int values[4];
+    values[4] = 0;
+
+Index within the array:
+
+    int values[4];
+    values[3] = 0;
+"#,
+    ),
+    (
+        "Q0910",
+        r#"A macro is defined again with a different body (-Wmacro-redefined).
+
+The new definition replaces the old one from that point on. Use #undef
+first to make the replacement explicit.
+
+Erroneous code example:
+
+    #define SIZE 10
+    #define SIZE 20
+
+Undefine the macro before redefining it:
+
+    #define SIZE 10
+    #undef SIZE
+    #define SIZE 20
+"#,
+    ),
+];
+
+/// The explanation of `code`, such as `Q0102`, if there is one. Case does
+/// not matter.
+pub fn explanation(code: &str) -> Option<&'static str> {
+    EXPLANATIONS
+        .iter()
+        .find(|(c, _)| c.eq_ignore_ascii_case(code))
+        .map(|(_, text)| *text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Every `"Qnnnn"` string literal in `src`.
+    fn codes(src: &str) -> Vec<&str> {
+        src.split('"')
+            .filter(|s| s.len() == 5 && s.starts_with('Q'))
+            .filter(|s| s[1..].bytes().all(|b| b.is_ascii_digit()))
+            .collect()
+    }
+
+    #[test]
+    fn lookup() {
+        assert!(explanation("Q0102").is_some());
+        assert_eq!(explanation("q0102"), explanation("Q0102"));
+        assert_eq!(explanation("Q9999"), None);
+    }
+
+    #[test]
+    fn codes_are_sorted_and_unique() {
+        for pair in EXPLANATIONS.windows(2) {
+            assert!(pair[0].0 < pair[1].0, "{} before {}", pair[0].0, pair[1].0);
+        }
+    }
+
+    #[test]
+    fn every_code_is_explained() {
+        let sources = [
+            include_str!("lexer.rs"),
+            include_str!("preprocessor.rs"),
+            include_str!("parser.rs"),
+            include_str!("checker.rs"),
+            include_str!("runtime.rs"),
+            include_str!("warning.rs"),
+            include_str!("interpreter.rs"),
+        ];
+
+        for code in sources.iter().flat_map(|src| codes(src)) {
+            assert!(explanation(code).is_some(), "{} is not explained", code);
+        }
+    }
+}
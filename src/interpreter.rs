@@ -0,0 +1,1001 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+use std::mem;
+use std::rc::Rc;
+
+use crate::diagnostic::Diagnostic;
+use crate::lexer::Span;
+use crate::parser::{
+    BinaryOp, Block, Decl, Expr, ExprKind, For, FunctionDef, IncDecOp, Initializer, Program, Stmt,
+    StructDef, Type, UnaryOp, VarDecl,
+};
+
+/// Calls that may be active at once before the program is stopped, so that
+/// runaway recursion is reported rather than overflowing the stack.
+const MAX_DEPTH: usize = 10_000;
+
+/// A value computed by the program. Arrays are shared by every name for
+/// them, as arguments are in C; structs are copied wherever C copies them.
+#[derive(Clone, Debug)]
+enum Value {
+    Void,
+    Char(i8),
+    Int(i32),
+    Float(f32),
+    Array(Rc<RefCell<Vec<Value>>>),
+    /// Members in declaration order.
+    Struct(Rc<RefCell<Vec<Value>>>),
+}
+
+impl Value {
+    fn int(&self) -> i32 {
+        match *self {
+            Value::Char(c) => c as i32,
+            Value::Int(i) => i,
+            Value::Float(f) => f as i32,
+            _ => unreachable!("checked to be arithmetic"),
+        }
+    }
+
+    fn float(&self) -> f32 {
+        match *self {
+            Value::Float(f) => f,
+            _ => self.int() as f32,
+        }
+    }
+
+    fn is_true(&self) -> bool {
+        match *self {
+            Value::Float(f) => f != 0.0,
+            _ => self.int() != 0,
+        }
+    }
+
+    /// `self` as a value of type `ty`, as in an assignment. Structs are
+    /// copied, arrays and their copies inside included.
+    fn convert(&self, ty: &Type) -> Value {
+        match ty {
+            Type::Void => Value::Void,
+            Type::Char => Value::Char(self.int() as i8),
+            Type::Int => Value::Int(self.int()),
+            Type::Float => Value::Float(self.float()),
+            Type::Struct(..) => self.copy(),
+            _ => self.clone(),
+        }
+    }
+
+    fn copy(&self) -> Value {
+        let copy = |items: &Rc<RefCell<Vec<Value>>>| {
+            Rc::new(RefCell::new(
+                items.borrow().iter().map(Value::copy).collect(),
+            ))
+        };
+
+        match self {
+            Value::Array(items) => Value::Array(copy(items)),
+            Value::Struct(members) => Value::Struct(copy(members)),
+            v => v.clone(),
+        }
+    }
+}
+
+/// Where a value is stored: a variable, or an element or member of an
+/// array or struct.
+enum Place {
+    Var(Rc<RefCell<Value>>),
+    Item(Rc<RefCell<Vec<Value>>>, usize),
+}
+
+impl Place {
+    fn get(&self) -> Value {
+        match self {
+            Place::Var(v) => v.borrow().clone(),
+            Place::Item(items, i) => items.borrow()[*i].clone(),
+        }
+    }
+
+    fn set(&self, value: Value) {
+        match self {
+            Place::Var(v) => *v.borrow_mut() = value,
+            Place::Item(items, i) => items.borrow_mut()[*i] = value,
+        }
+    }
+}
+
+/// How a statement finished.
+enum Flow {
+    Normal,
+    Break,
+    Continue,
+    Return(Value),
+}
+
+/// A fault that stops the program, such as division by zero.
+#[derive(Debug)]
+pub struct RuntimeError {
+    pub span: Span,
+    /// Diagnostic code; none for failures of the interpreter's own input or
+    /// output, and for internal errors, which are not the program's fault.
+    pub code: Option<&'static str>,
+    pub message: String,
+}
+
+impl From<RuntimeError> for Diagnostic {
+    fn from(err: RuntimeError) -> Self {
+        let d = Diagnostic::error(err.span, err.message);
+
+        match err.code {
+            Some(code) => d.with_code(code),
+            None => d,
+        }
+    }
+}
+
+type Result<T> = std::result::Result<T, RuntimeError>;
+
+fn error<T>(span: &Span, code: &'static str, message: impl Into<String>) -> Result<T> {
+    Err(RuntimeError {
+        span: span.clone(),
+        code: Some(code),
+        message: message.into(),
+    })
+}
+
+fn io_error(span: &Span, err: io::Error) -> RuntimeError {
+    RuntimeError {
+        span: span.clone(),
+        code: None,
+        message: format!("input or output failed: {}", err),
+    }
+}
+
+/// Something the checker guarantees does not hold. Reported like a fault
+/// so that a checker bug does not lose what the program has written.
+fn internal<T>(span: &Span, message: &str) -> Result<T> {
+    Err(RuntimeError {
+        span: span.clone(),
+        code: None,
+        message: format!("internal error: {}", message),
+    })
+}
+
+/// The argument of a runtime function taking one number, which a custom
+/// runtime header might have declared differently.
+fn number_arg<'v>(span: &Span, name: &str, args: &'v [Value]) -> Result<&'v Value> {
+    match args {
+        [arg @ (Value::Char(_) | Value::Int(_) | Value::Float(_))] => Ok(arg),
+        _ => internal(span, &format!("{} takes one number", name)),
+    }
+}
+
+/// Type the checker recorded for `e`.
+fn ty(e: &Expr) -> Result<&Type> {
+    match e.ty.get() {
+        Some(ty) => Ok(ty),
+        None => internal(&e.span, "expression was not checked"),
+    }
+}
+
+#[derive(Default)]
+struct Scope<'a> {
+    variables: HashMap<&'a str, Rc<RefCell<Value>>>,
+}
+
+/// Runs a checked program by walking its AST, with the runtime library's
+/// I/O functions reading `input` and writing `output`.
+///
+/// Arithmetic follows C: `char` is signed and 8 bits wide, `int` 32 bits
+/// and wraps on overflow, `float` single precision, and integer division
+/// truncates toward zero. Variables without an initializer start at zero.
+pub struct Interpreter<'a> {
+    functions: HashMap<&'a str, &'a FunctionDef>,
+    globals: HashMap<&'a str, Rc<RefCell<Value>>>,
+    /// Struct definitions run so far, by the number in `Type::Struct`.
+    structs: HashMap<usize, &'a StructDef>,
+    /// Block scopes of the function being run, innermost last.
+    scopes: Vec<Scope<'a>>,
+    /// Number of calls active.
+    depth: usize,
+    input: Box<dyn BufRead + 'a>,
+    output: Box<dyn Write + 'a>,
+}
+
+impl<'a> Interpreter<'a> {
+    pub fn new(input: impl BufRead + 'a, output: impl Write + 'a) -> Self {
+        Interpreter {
+            functions: HashMap::new(),
+            globals: HashMap::new(),
+            structs: HashMap::new(),
+            scopes: Vec::new(),
+            depth: 0,
+            input: Box::new(input),
+            output: Box::new(output),
+        }
+    }
+
+    /// Initializes the globals and calls `main`, which must be declared as
+    /// `int main()`, returning what it returns, or `None` if the program has
+    /// no `main`.
+    pub fn run(mut self, program: &'a Program) -> Result<Option<i32>> {
+        for decl in &program.decls {
+            if let Decl::Function(f) = decl {
+                if f.body.is_some() {
+                    self.functions.insert(&f.name, f);
+                }
+            }
+        }
+
+        let Some(main) = self.functions.get("main").copied() else {
+            return Ok(None);
+        };
+
+        // the checker accepts any main, as a library may have one, but only
+        // this one can be run
+        if main.return_type != Type::Int || !main.params.is_empty() {
+            return error(&main.span, "Q0705", "main must be declared as int main()");
+        }
+
+        // every global exists, as zero, before any initializer runs
+        for decl in &program.decls {
+            match decl {
+                Decl::Var(vars) => {
+                    for v in vars {
+                        let value = self.zero(&v.ty, &v.span)?;
+                        self.globals.insert(&v.name, Rc::new(RefCell::new(value)));
+                    }
+                }
+                Decl::Struct(def) => {
+                    self.structs.insert(def.id, def);
+                }
+                Decl::Function(_) => {}
+            }
+        }
+
+        for decl in &program.decls {
+            if let Decl::Var(vars) = decl {
+                for v in vars {
+                    let var = self.lookup(&v.name, &v.span)?;
+                    self.initialize(v, &var)?;
+                }
+            }
+        }
+
+        let status = self.call_function(&main.span, main, Vec::new());
+        // what the program wrote before a fault is still shown
+        self.output
+            .flush()
+            .map_err(|err| io_error(&main.span, err))?;
+        let status = status?;
+
+        match status {
+            Value::Char(_) | Value::Int(_) | Value::Float(_) => Ok(Some(status.int())),
+            _ => internal(&main.span, "main did not return an int"),
+        }
+    }
+
+    /// The variable `name`, used at `span`.
+    fn lookup(&self, name: &str, span: &Span) -> Result<Rc<RefCell<Value>>> {
+        let var = self
+            .scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.variables.get(name))
+            .or_else(|| self.globals.get(name));
+
+        match var {
+            Some(var) => Ok(var.clone()),
+            None => internal(span, &format!("{} is not declared", name)),
+        }
+    }
+
+    fn lookup_struct(&self, id: Option<usize>, span: &Span) -> Result<&'a StructDef> {
+        match id.and_then(|id| self.structs.get(&id)) {
+            Some(def) => Ok(def),
+            None => internal(span, "struct type is incomplete"),
+        }
+    }
+
+    /// The value a variable of type `ty`, declared at `span`, starts with
+    /// when not initialized.
+    fn zero(&self, ty: &Type, span: &Span) -> Result<Value> {
+        let shared = |items: Vec<Value>| Rc::new(RefCell::new(items));
+
+        Ok(match ty {
+            Type::Char => Value::Char(0),
+            Type::Int => Value::Int(0),
+            Type::Float => Value::Float(0.0),
+            Type::Array(elem, len) => {
+                let Some(len) = len else {
+                    return internal(span, "array length is missing");
+                };
+                let items = (0..*len).map(|_| self.zero(elem, span));
+                Value::Array(shared(items.collect::<Result<_>>()?))
+            }
+            Type::Struct(_, id) => {
+                let def = self.lookup_struct(*id, span)?;
+                let members = def.members.iter().map(|m| self.zero(&m.ty, span));
+                Value::Struct(shared(members.collect::<Result<_>>()?))
+            }
+            Type::Void | Type::Error => Value::Void,
+        })
+    }
+
+    /// Gives the variable `v` declares, already in scope as `var`, the value
+    /// of its initializer. Like the checker, the initializer sees the
+    /// variable itself, as `int x = x;` does in C.
+    fn initialize(&mut self, v: &'a VarDecl, var: &Rc<RefCell<Value>>) -> Result<()> {
+        if let Some(init) = &v.init {
+            let value = self.initializer(&v.ty, init)?;
+            *var.borrow_mut() = value;
+        }
+
+        Ok(())
+    }
+
+    /// The value `init` gives a variable of type `ty`. Elements and members
+    /// a brace list leaves out are zero, as is the rest of a `char` array
+    /// after a string.
+    fn initializer(&mut self, ty: &Type, init: &'a Initializer) -> Result<Value> {
+        let items = match init {
+            Initializer::Expr(e) => {
+                if let (ExprKind::StrLit(s), Type::Array(..)) = (&e.kind, ty) {
+                    let Value::Array(chars) = self.zero(ty, &e.span)? else {
+                        return internal(&e.span, "array has no elements");
+                    };
+
+                    for (c, &b) in chars.borrow_mut().iter_mut().zip(s) {
+                        *c = Value::Char(b as i8);
+                    }
+
+                    return Ok(Value::Array(chars));
+                }
+
+                return Ok(self.expr(e)?.convert(ty));
+            }
+            Initializer::List(items, _) => items,
+        };
+
+        let value = self.zero(ty, init.span())?;
+
+        let (slots, types): (_, Vec<Type>) = match (&value, ty) {
+            (Value::Array(slots), Type::Array(elem, _)) => {
+                (slots.clone(), vec![(**elem).clone(); items.len()])
+            }
+            (Value::Struct(slots), Type::Struct(_, id)) => {
+                let def = self.lookup_struct(*id, init.span())?;
+                (
+                    slots.clone(),
+                    def.members.iter().map(|m| m.ty.clone()).collect(),
+                )
+            }
+            // `int x = { 1 };`
+            _ => {
+                return match items.first() {
+                    Some(item) => self.initializer(ty, item),
+                    None => Ok(value),
+                };
+            }
+        };
+
+        for (i, (item, ty)) in items.iter().zip(&types).enumerate() {
+            let item = self.initializer(ty, item)?;
+            slots.borrow_mut()[i] = item;
+        }
+
+        Ok(value)
+    }
+
+    /// Declares a local in the innermost scope, returning where it is
+    /// stored.
+    fn declare(&mut self, v: &'a VarDecl, value: Value) -> Result<Rc<RefCell<Value>>> {
+        let Some(scope) = self.scopes.last_mut() else {
+            return internal(&v.span, "local declared outside a function");
+        };
+
+        let var = Rc::new(RefCell::new(value));
+        scope.variables.insert(&v.name, var.clone());
+        Ok(var)
+    }
+
+    /// Calls `f` from `span`.
+    fn call_function(
+        &mut self,
+        span: &Span,
+        f: &'a FunctionDef,
+        args: Vec<Value>,
+    ) -> Result<Value> {
+        if self.depth == MAX_DEPTH {
+            return error(
+                span,
+                "Q0704",
+                format!("more than {} calls active at once", MAX_DEPTH),
+            );
+        }
+
+        let Some(body) = &f.body else {
+            return internal(span, &format!("{} has no body", f.name));
+        };
+
+        // the caller's locals are out of sight until the call returns
+        let caller = mem::replace(&mut self.scopes, vec![Scope::default()]);
+        self.depth += 1;
+
+        let flow = f
+            .params
+            .iter()
+            .zip(args)
+            .try_for_each(|(p, arg)| self.declare(p, arg.convert(&p.ty)).map(drop))
+            .and_then(|()| self.statements(&body.stmts));
+
+        self.depth -= 1;
+        self.scopes = caller;
+
+        match flow? {
+            Flow::Return(v) => Ok(v.convert(&f.return_type)),
+            // reaching the end of a function returns zero, which is what
+            // main returns in C
+            _ => self.zero(&f.return_type, &f.span),
+        }
+    }
+
+    fn statements(&mut self, stmts: &'a [Stmt]) -> Result<Flow> {
+        for stmt in stmts {
+            match self.statement(stmt)? {
+                Flow::Normal => {}
+                flow => return Ok(flow),
+            }
+        }
+
+        Ok(Flow::Normal)
+    }
+
+    fn block(&mut self, b: &'a Block) -> Result<Flow> {
+        self.scopes.push(Scope::default());
+        let flow = self.statements(&b.stmts);
+        self.scopes.pop();
+        flow
+    }
+
+    /// Runs `body` as the body of a loop: `Some` with how to leave the loop,
+    /// or `None` to go on with it.
+    fn loop_body(&mut self, body: &'a Stmt) -> Result<Option<Flow>> {
+        Ok(match self.statement(body)? {
+            Flow::Break => Some(Flow::Normal),
+            Flow::Return(v) => Some(Flow::Return(v)),
+            Flow::Normal | Flow::Continue => None,
+        })
+    }
+
+    fn statement(&mut self, stmt: &'a Stmt) -> Result<Flow> {
+        match stmt {
+            Stmt::Expr(e) => {
+                self.expr(e)?;
+            }
+            Stmt::Var(vars) => {
+                for v in vars {
+                    let value = self.zero(&v.ty, &v.span)?;
+                    let var = self.declare(v, value)?;
+                    self.initialize(v, &var)?;
+                }
+            }
+            Stmt::Struct(def) => {
+                self.structs.insert(def.id, def);
+            }
+            Stmt::Block(b) => return self.block(b),
+            Stmt::If(s) => {
+                if self.expr(&s.cond)?.is_true() {
+                    return self.statement(&s.then);
+                } else if let Some(els) = &s.els {
+                    return self.statement(els);
+                }
+            }
+            Stmt::For(s) => {
+                self.scopes.push(Scope::default());
+                let flow = self.for_loop(s);
+                self.scopes.pop();
+                return flow;
+            }
+            Stmt::While(s) => {
+                while self.expr(&s.cond)?.is_true() {
+                    if let Some(flow) = self.loop_body(&s.body)? {
+                        return Ok(flow);
+                    }
+                }
+            }
+            Stmt::DoWhile(s) => loop {
+                if let Some(flow) = self.loop_body(&s.body)? {
+                    return Ok(flow);
+                }
+
+                if !self.expr(&s.cond)?.is_true() {
+                    break;
+                }
+            },
+            Stmt::Return(r) => {
+                let value = match &r.value {
+                    Some(e) => self.expr(e)?,
+                    None => Value::Void,
+                };
+
+                return Ok(Flow::Return(value));
+            }
+            Stmt::Break(_) => return Ok(Flow::Break),
+            Stmt::Continue(_) => return Ok(Flow::Continue),
+            Stmt::Empty(_) => {}
+        }
+
+        Ok(Flow::Normal)
+    }
+
+    fn for_loop(&mut self, s: &'a For) -> Result<Flow> {
+        if let Some(init) = &s.init {
+            self.statement(init)?;
+        }
+
+        loop {
+            if let Some(cond) = &s.cond {
+                if !self.expr(cond)?.is_true() {
+                    return Ok(Flow::Normal);
+                }
+            }
+
+            if let Some(flow) = self.loop_body(&s.body)? {
+                return Ok(flow);
+            }
+
+            if let Some(step) = &s.step {
+                self.expr(step)?;
+            }
+        }
+    }
+
+    /// Where the object `e` designates is stored. Array elements are
+    /// checked against the array's length.
+    fn place(&mut self, e: &'a Expr) -> Result<Place> {
+        match &e.kind {
+            ExprKind::Ident(name) => Ok(Place::Var(self.lookup(name, &e.span)?)),
+            ExprKind::Index { array, index } => {
+                let Value::Array(items) = self.expr(array)? else {
+                    return internal(&array.span, "subscripted value is not an array");
+                };
+                let i = self.expr(index)?.int();
+                let len = items.borrow().len();
+
+                if i < 0 || i as usize >= len {
+                    return error(
+                        &index.span,
+                        "Q0702",
+                        format!(
+                            "index {} is out of bounds for an array of length {}",
+                            i, len
+                        ),
+                    );
+                }
+
+                Ok(Place::Item(items, i as usize))
+            }
+            ExprKind::Member { base, member, .. } => {
+                let Type::Struct(_, id) = ty(base)? else {
+                    return internal(&base.span, "member access on a value that is not a struct");
+                };
+                let def = self.lookup_struct(*id, &base.span)?;
+                let Some(i) = def.members.iter().position(|m| m.name == *member) else {
+                    return internal(&e.span, &format!("no member named {}", member));
+                };
+
+                let Value::Struct(members) = self.expr(base)? else {
+                    return internal(&base.span, "member access on a value that is not a struct");
+                };
+
+                // a struct of a different definition can be shorter
+                if i >= members.borrow().len() {
+                    return internal(&e.span, &format!("no member named {}", member));
+                }
+
+                Ok(Place::Item(members, i))
+            }
+            _ => internal(&e.span, "expression is not an lvalue"),
+        }
+    }
+
+    fn expr(&mut self, e: &'a Expr) -> Result<Value> {
+        match &e.kind {
+            ExprKind::IntLit(i) => Ok(Value::Int(*i as i32)),
+            ExprKind::RealLit(f) => Ok(Value::Float(*f as f32)),
+            ExprKind::CharLit(c) => Ok(Value::Char(*c as i8)),
+            ExprKind::StrLit(s) => {
+                let chars = s.iter().chain([&0]).map(|&b| Value::Char(b as i8));
+                Ok(Value::Array(Rc::new(RefCell::new(chars.collect()))))
+            }
+            ExprKind::Ident(_) | ExprKind::Index { .. } | ExprKind::Member { .. } => {
+                Ok(self.place(e)?.get())
+            }
+            ExprKind::Unary { op, operand } => {
+                let v = self.expr(operand)?;
+
+                Ok(match op {
+                    UnaryOp::Not => Value::Int(!v.is_true() as i32),
+                    UnaryOp::BitNot => Value::Int(!v.int()),
+                    UnaryOp::Neg => match v {
+                        Value::Float(f) => Value::Float(-f),
+                        v => Value::Int(v.int().wrapping_neg()),
+                    },
+                    UnaryOp::Plus => v.convert(ty(e)?),
+                    UnaryOp::AddrOf | UnaryOp::Deref => {
+                        return internal(&e.span, &format!("unary {} is not supported", op));
+                    }
+                })
+            }
+            ExprKind::IncDec { op, operand } => {
+                let place = self.place(operand)?;
+                let old = place.get();
+                let delta = match op {
+                    IncDecOp::PreIncr | IncDecOp::PostIncr => BinaryOp::Add,
+                    IncDecOp::PreDecr | IncDecOp::PostDecr => BinaryOp::Sub,
+                };
+                let new = self
+                    .arithmetic(e, delta, &old, &Value::Int(1))?
+                    .convert(ty(operand)?);
+                place.set(new.clone());
+
+                Ok(match op {
+                    IncDecOp::PreIncr | IncDecOp::PreDecr => new,
+                    IncDecOp::PostIncr | IncDecOp::PostDecr => old,
+                })
+            }
+            ExprKind::Binary { op, lhs, rhs } => {
+                let l = self.expr(lhs)?;
+
+                match op {
+                    BinaryOp::LogAnd if !l.is_true() => return Ok(Value::Int(0)),
+                    BinaryOp::LogOr if l.is_true() => return Ok(Value::Int(1)),
+                    BinaryOp::LogAnd | BinaryOp::LogOr => {
+                        return Ok(Value::Int(self.expr(rhs)?.is_true() as i32));
+                    }
+                    _ => {}
+                }
+
+                let r = self.expr(rhs)?;
+                Ok(self.arithmetic(e, *op, &l, &r)?.convert(ty(e)?))
+            }
+            ExprKind::Assign { op, lhs, rhs } => {
+                let place = self.place(lhs)?;
+                let r = self.expr(rhs)?;
+
+                let value = match op {
+                    Some(op) => self.arithmetic(e, *op, &place.get(), &r)?,
+                    None => r,
+                };
+                let value = value.convert(ty(lhs)?);
+                place.set(value.clone());
+
+                Ok(value)
+            }
+            ExprKind::Ternary { cond, then, els } => {
+                let branch = if self.expr(cond)?.is_true() {
+                    then
+                } else {
+                    els
+                };
+
+                Ok(self.expr(branch)?.convert(ty(e)?))
+            }
+            ExprKind::Cast { ty, expr } => Ok(self.expr(expr)?.convert(ty)),
+            ExprKind::Call { name, args, .. } => {
+                let mut values = Vec::with_capacity(args.len());
+
+                for arg in args {
+                    values.push(self.expr(arg)?);
+                }
+
+                match self.functions.get(name.as_str()) {
+                    Some(f) => self.call_function(&e.span, f, values),
+                    None => self.builtin(e, name, &values),
+                }
+            }
+        }
+    }
+
+    /// `l op r` for an arithmetic, bitwise, shift or comparison operator,
+    /// computed in the common type of the operands; comparisons give an
+    /// int.
+    fn arithmetic(&self, e: &Expr, op: BinaryOp, l: &Value, r: &Value) -> Result<Value> {
+        let float = |v: &Value| matches!(v, Value::Float(_));
+
+        if float(l) || float(r) {
+            let (a, b) = (l.float(), r.float());
+
+            return Ok(match op {
+                BinaryOp::Add => Value::Float(a + b),
+                BinaryOp::Sub => Value::Float(a - b),
+                BinaryOp::Mul => Value::Float(a * b),
+                BinaryOp::Div => Value::Float(a / b),
+                _ => Value::Int(compare(op, a, b) as i32),
+            });
+        }
+
+        let (a, b) = (l.int(), r.int());
+
+        if matches!(op, BinaryOp::Div | BinaryOp::Mod) && b == 0 {
+            let what = if op == BinaryOp::Div {
+                "division"
+            } else {
+                "remainder"
+            };
+
+            return error(&e.span, "Q0701", format!("{} by zero", what));
+        }
+
+        Ok(Value::Int(match op {
+            BinaryOp::Add => a.wrapping_add(b),
+            BinaryOp::Sub => a.wrapping_sub(b),
+            BinaryOp::Mul => a.wrapping_mul(b),
+            BinaryOp::Div => a.wrapping_div(b),
+            BinaryOp::Mod => a.wrapping_rem(b),
+            BinaryOp::BitAnd => a & b,
+            BinaryOp::BitOr => a | b,
+            BinaryOp::BitXor => a ^ b,
+            BinaryOp::Shl => a.wrapping_shl(b as u32),
+            BinaryOp::Shr => a.wrapping_shr(b as u32),
+            _ => compare(op, a, b) as i32,
+        }))
+    }
+
+    /// Calls a runtime library function the program does not define.
+    fn builtin(&mut self, e: &Expr, name: &str, args: &[Value]) -> Result<Value> {
+        let span = &e.span;
+        let io = |err| io_error(span, err);
+
+        match name {
+            "getchar" => {
+                self.output.flush().map_err(io)?;
+                let c = self.peek().map_err(io)?;
+
+                if c.is_some() {
+                    self.input.consume(1);
+                }
+
+                Ok(Value::Int(c.map_or(-1, i32::from)))
+            }
+            "putchar" => {
+                let c = number_arg(span, name, args)?.int();
+                self.output.write_all(&[c as u8]).map_err(io)?;
+                Ok(Value::Int(c))
+            }
+            "getint" => {
+                let word = self.read_number(|c| c.is_ascii_digit()).map_err(io)?;
+                let value = word.parse::<i64>().map_or(0, |n| n as i32);
+                Ok(Value::Int(value))
+            }
+            "putint" => {
+                write!(self.output, "{}", number_arg(span, name, args)?.int()).map_err(io)?;
+                Ok(Value::Void)
+            }
+            "getfloat" => {
+                let word = self
+                    .read_number(|c| c.is_ascii_digit() || matches!(c, b'.' | b'e' | b'E'))
+                    .map_err(io)?;
+                Ok(Value::Float(word.parse().unwrap_or(0.0)))
+            }
+            "putfloat" => {
+                write!(self.output, "{:.6}", number_arg(span, name, args)?.float()).map_err(io)?;
+                Ok(Value::Void)
+            }
+            "putstring" => {
+                let [Value::Array(chars)] = args else {
+                    return internal(span, "putstring takes one array");
+                };
+                let bytes: Vec<u8> = chars
+                    .borrow()
+                    .iter()
+                    .map(|c| c.int() as u8)
+                    .take_while(|&b| b != 0)
+                    .collect();
+                self.output.write_all(&bytes).map_err(io)?;
+                Ok(Value::Void)
+            }
+            _ => error(
+                span,
+                "Q0703",
+                format!("function {} is declared but never defined", name),
+            ),
+        }
+    }
+
+    fn peek(&mut self) -> io::Result<Option<u8>> {
+        Ok(self.input.fill_buf()?.first().copied())
+    }
+
+    /// Skips white space and reads an optionally signed number whose other
+    /// characters satisfy `digit`, as `scanf` would. Nothing is consumed
+    /// past the number.
+    fn read_number(&mut self, digit: impl Fn(u8) -> bool) -> io::Result<String> {
+        self.output.flush()?;
+
+        while self.peek()?.is_some_and(|c| c.is_ascii_whitespace()) {
+            self.input.consume(1);
+        }
+
+        let mut word = String::new();
+
+        while let Some(c) = self.peek()? {
+            let sign = matches!(c, b'+' | b'-') && (word.is_empty() || word.ends_with(['e', 'E']));
+
+            if !digit(c) && !sign {
+                break;
+            }
+
+            word.push(c as char);
+            self.input.consume(1);
+        }
+
+        Ok(word)
+    }
+}
+
+fn compare<T: PartialOrd>(op: BinaryOp, a: T, b: T) -> bool {
+    match op {
+        BinaryOp::Eq => a == b,
+        BinaryOp::Ne => a != b,
+        BinaryOp::Lt => a < b,
+        BinaryOp::Le => a <= b,
+        BinaryOp::Gt => a > b,
+        BinaryOp::Ge => a >= b,
+        _ => unreachable!("{} is not a comparison", op),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::checker::Checker;
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+    use crate::runtime::Runtime;
+
+    /// Checks and runs `src` with `input` on standard input, returning how
+    /// it ended and what it wrote.
+    fn run(src: &str, input: &str) -> (Result<Option<i32>>, String) {
+        let lexemes = Lexer::new("test.c", src).lex().expect("input lexes");
+        let (program, errors) = Parser::new(lexemes).parse();
+        assert!(errors.is_empty(), "unexpected errors: {:?}", errors);
+
+        let diagnostics = Checker::check(&program, &Runtime::bundled());
+        let errors: Vec<&Diagnostic> = diagnostics.iter().filter(|d| d.is_error()).collect();
+        assert!(errors.is_empty(), "unexpected errors: {:?}", errors);
+
+        let mut output = Vec::new();
+        let status = Interpreter::new(input.as_bytes(), &mut output).run(&program);
+
+        (status, String::from_utf8(output).unwrap())
+    }
+
+    fn output(src: &str, input: &str) -> String {
+        let (status, output) = run(src, input);
+        assert_eq!(status.unwrap(), Some(0));
+        output
+    }
+
+    fn error_code(src: &str) -> Option<&'static str> {
+        run(src, "").0.expect_err("program faults").code
+    }
+
+    #[test]
+    fn exit_status() {
+        assert_eq!(run("int main() { return 7; }", "").0.unwrap(), Some(7));
+        assert_eq!(run("int main() { }", "").0.unwrap(), Some(0));
+        assert_eq!(run("int f() { return 1; }", "").0.unwrap(), None);
+    }
+
+    #[test]
+    fn arithmetic() {
+        let src = "int main() {\n\
+                       char c = 127;\n\
+                       int big = 2147483647;\n\
+                       c++;\n\
+                       putint(c); putchar(' ');\n\
+                       putint(big + 1); putchar(' ');\n\
+                       putint(-7 / 2); putchar(' ');\n\
+                       putint(-7 % 2); putchar(' ');\n\
+                       putfloat(1 / 4.0);\n\
+                       return 0;\n\
+                   }";
+
+        assert_eq!(output(src, ""), "-128 -2147483648 -3 -1 0.250000");
+    }
+
+    #[test]
+    fn control_flow() {
+        let src = "int fib(int n) { if (n < 2) return n; return fib(n - 1) + fib(n - 2); }\n\
+                   int main() {\n\
+                       int i;\n\
+                       for (i = 0; i < 10; i++) {\n\
+                           if (i % 2) continue;\n\
+                           if (i > 6) break;\n\
+                           putint(fib(i));\n\
+                       }\n\
+                       do i--; while (i > 5);\n\
+                       putint(i);\n\
+                       return 0;\n\
+                   }";
+
+        assert_eq!(output(src, ""), "01385");
+    }
+
+    #[test]
+    fn arrays_are_shared_and_structs_copied() {
+        let src = "struct P { int x; int v[2]; };\n\
+                   void set(int a[], struct P p) { a[0] = 5; p.x = 5; p.v[0] = 5; }\n\
+                   int main() {\n\
+                       int a[2];\n\
+                       struct P p = { 1, { 2, 3 } };\n\
+                       struct P q = p;\n\
+                       q.v[1] = 9;\n\
+                       set(a, p);\n\
+                       putint(a[0]); putint(p.x); putint(p.v[0]); putint(p.v[1]);\n\
+                       return 0;\n\
+                   }";
+
+        assert_eq!(output(src, ""), "5123");
+    }
+
+    #[test]
+    fn input() {
+        let src = "int main() {\n\
+                       int n = getint();\n\
+                       float f = getfloat();\n\
+                       int c = getchar();\n\
+                       putint(n * 2); putfloat(f); putint(c); putint(getchar());\n\
+                       return 0;\n\
+                   }";
+
+        assert_eq!(output(src, " -21\n1.5e1!"), "-4215.00000033-1");
+    }
+
+    #[test]
+    fn strings() {
+        let src = "char greeting[8] = \"hi\";\n\
+                   int main() { putstring(greeting); putstring(\" there\"); return 0; }";
+
+        assert_eq!(output(src, ""), "hi there");
+    }
+
+    #[test]
+    fn variables_exist_in_their_own_initializer() {
+        let src = "int main() { int y = y + 1; putint(y); return 0; }";
+
+        assert_eq!(output(src, ""), "1");
+    }
+
+    #[test]
+    fn globals_are_initialized_before_main() {
+        let src = "int g = 2 * 3; int z; int main() { putint(g + z); return 0; }";
+
+        assert_eq!(output(src, ""), "6");
+    }
+
+    #[test]
+    fn faults() {
+        assert_eq!(
+            error_code("int main() { int z = 0; return 1 / z; }"),
+            Some("Q0701")
+        );
+        assert_eq!(
+            error_code("int main() { int a[2]; return a[2]; }"),
+            Some("Q0702")
+        );
+        assert_eq!(
+            error_code("int f(); int main() { return f(); }"),
+            Some("Q0703")
+        );
+    }
+
+    #[test]
+    fn output_before_a_fault_is_kept() {
+        let (status, output) = run("int main() { putint(1); return 1 / 0; }", "");
+
+        assert!(status.is_err());
+        assert_eq!(output, "1");
+    }
+}
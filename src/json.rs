@@ -0,0 +1,276 @@
+use std::fmt::Write as _;
+use std::io::{self, Write};
+
+use crate::diagnostic::{Diagnostic, Fix, Severity};
+use crate::lexer::Span;
+
+const SARIF_SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+/// `s` as a JSON string literal.
+fn string(s: &str) -> String {
+    let mut out = String::from("\"");
+
+    for c in s.chars() {
+        match c {
+            '"' => out += "\\\"",
+            '\\' => out += "\\\\",
+            '\n' => out += "\\n",
+            '\r' => out += "\\r",
+            '\t' => out += "\\t",
+            c if (c as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+
+    out.push('"');
+    out
+}
+
+fn strings<'a>(items: impl IntoIterator<Item = &'a String>) -> String {
+    let items: Vec<String> = items.into_iter().map(|s| string(s)).collect();
+    format!("[{}]", items.join(","))
+}
+
+/// The name of the warning `d` is, or null.
+fn warning(d: &Diagnostic) -> String {
+    d.warning.map_or("null".to_string(), |w| string(w.name()))
+}
+
+fn severity(d: &Diagnostic) -> &'static str {
+    match d.severity {
+        Severity::Error => "error",
+        Severity::Warning => "warning",
+    }
+}
+
+/// The fields locating `span`; spans with no line, such as a file that
+/// could not be opened, only give the file. Columns count from 1 and the
+/// end column is just past the last character.
+fn location(span: &Span) -> String {
+    let mut out = format!("\"file\":{}", string(&span.infile_name));
+
+    if span.lineno != 0 {
+        let _ = write!(
+            out,
+            ",\"line\":{},\"column\":{},\"end_line\":{},\"end_column\":{}",
+            span.lineno, span.col, span.end_lineno, span.end_col
+        );
+    }
+
+    out
+}
+
+/// One JSON object per diagnostic, in an array:
+///
+/// ```text
+/// [
+/// {"code":null,"severity":"error","warning":null,"message":"undeclared identifier",...},
+/// ...
+/// ]
+/// ```
+pub fn write_json(out: &mut dyn Write, diagnostics: &[Diagnostic]) -> io::Result<()> {
+    writeln!(out, "[")?;
+
+    for (i, d) in diagnostics.iter().enumerate() {
+        let code = d.code.map_or("null".to_string(), string);
+        let labels: Vec<String> = d
+            .labels
+            .iter()
+            .map(|l| {
+                format!(
+                    "{{{},\"message\":{}}}",
+                    location(&l.span),
+                    string(&l.message)
+                )
+            })
+            .collect();
+        let help = d.help.as_deref().map_or("null".to_string(), string);
+        let fixes: Vec<String> = d
+            .fixes
+            .iter()
+            .map(|f| {
+                format!(
+                    "{{{},\"replacement\":{},\"applicable\":{}}}",
+                    location(&f.span),
+                    string(&f.replacement),
+                    f.applicable
+                )
+            })
+            .collect();
+
+        write!(
+            out,
+            "{{\"code\":{},\"severity\":\"{}\",\"warning\":{},\"message\":{},{},\"labels\":[{}],\"notes\":{},\"help\":{},\"fixes\":[{}]}}",
+            code,
+            severity(d),
+            warning(d),
+            string(&d.message),
+            location(&d.span),
+            labels.join(","),
+            strings(&d.notes),
+            help,
+            fixes.join(",")
+        )?;
+
+        writeln!(out, "{}", if i + 1 < diagnostics.len() { "," } else { "" })?;
+    }
+
+    writeln!(out, "]")
+}
+
+fn sarif_region(span: &Span) -> String {
+    format!(
+        "{{\"startLine\":{},\"startColumn\":{},\"endLine\":{},\"endColumn\":{}}}",
+        span.lineno, span.col, span.end_lineno, span.end_col
+    )
+}
+
+fn sarif_location(span: &Span) -> String {
+    let mut out = format!(
+        "\"physicalLocation\":{{\"artifactLocation\":{{\"uri\":{}}}",
+        string(&span.infile_name)
+    );
+
+    if span.lineno != 0 {
+        let _ = write!(out, ",\"region\":{}", sarif_region(span));
+    }
+
+    out + "}"
+}
+
+/// A SARIF fix object replacing the region `fix` covers.
+fn sarif_fix(fix: &Fix) -> String {
+    format!(
+        "{{\"description\":{{\"text\":{}}},\"artifactChanges\":[{{\"artifactLocation\":{{\"uri\":{}}},\"replacements\":[{{\"deletedRegion\":{},\"insertedContent\":{{\"text\":{}}}}}]}}]}}",
+        string(&fix.description()),
+        string(&fix.span.infile_name),
+        sarif_region(&fix.span),
+        string(&fix.replacement)
+    )
+}
+
+/// A SARIF 2.1.0 log with one run and one result per diagnostic. Labels
+/// become related locations and suggested edits become fixes; the warning
+/// name, notes and help go in the result's property bag.
+pub fn write_sarif(out: &mut dyn Write, diagnostics: &[Diagnostic]) -> io::Result<()> {
+    let mut results = Vec::new();
+
+    for d in diagnostics {
+        let mut result = String::from("{");
+
+        if let Some(code) = d.code {
+            let _ = write!(result, "\"ruleId\":{},", string(code));
+        }
+
+        let related: Vec<String> = d
+            .labels
+            .iter()
+            .enumerate()
+            .map(|(i, l)| {
+                format!(
+                    "{{\"id\":{},{},\"message\":{{\"text\":{}}}}}",
+                    i,
+                    sarif_location(&l.span),
+                    string(&l.message)
+                )
+            })
+            .collect();
+        let fixes: Vec<String> = d.fixes.iter().map(sarif_fix).collect();
+
+        let _ = write!(
+            result,
+            "\"level\":\"{}\",\"message\":{{\"text\":{}}},\"locations\":[{{{}}}],\"relatedLocations\":[{}],\"fixes\":[{}],\"properties\":{{\"warning\":{},\"notes\":{},\"help\":{}}}}}",
+            severity(d),
+            string(&d.message),
+            sarif_location(&d.span),
+            related.join(","),
+            fixes.join(","),
+            warning(d),
+            strings(&d.notes),
+            d.help.as_deref().map_or("null".to_string(), string)
+        );
+
+        results.push(result);
+    }
+
+    writeln!(out, "{{")?;
+    writeln!(out, "\"$schema\":{},", string(SARIF_SCHEMA))?;
+    writeln!(out, "\"version\":\"2.1.0\",")?;
+    writeln!(out, "\"runs\":[{{")?;
+    writeln!(
+        out,
+        "\"tool\":{{\"driver\":{{\"name\":{},\"version\":{}}}}},",
+        string(env!("CARGO_PKG_NAME")),
+        string(env!("CARGO_PKG_VERSION"))
+    )?;
+    writeln!(out, "\"results\":[")?;
+
+    if !results.is_empty() {
+        writeln!(out, "{}", results.join(",\n"))?;
+    }
+
+    writeln!(out, "]")?;
+    writeln!(out, "}}]")?;
+    writeln!(out, "}}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn diagnostic() -> Diagnostic {
+        let span = Span {
+            infile_name: "a.c".into(),
+            lineno: 2,
+            col: 5,
+            end_lineno: 2,
+            end_col: 6,
+            start: 11,
+            end: 12,
+        };
+
+        Diagnostic::error(span.clone(), "undeclared \"x\"")
+            .with_help("declare it")
+            .with_fix(span, "y", true)
+    }
+
+    fn output(write: fn(&mut dyn Write, &[Diagnostic]) -> io::Result<()>) -> String {
+        let mut out = Vec::new();
+        write(&mut out, &[diagnostic()]).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn strings_are_escaped() {
+        assert_eq!(string("a\"b\\c\n\u{1}"), "\"a\\\"b\\\\c\\n\\u0001\"");
+    }
+
+    #[test]
+    fn json() {
+        assert_eq!(
+            output(write_json),
+            "[\n\
+             {\"code\":null,\"severity\":\"error\",\"warning\":null,\"message\":\"undeclared \\\"x\\\"\",\
+             \"file\":\"a.c\",\"line\":2,\"column\":5,\"end_line\":2,\"end_column\":6,\
+             \"labels\":[],\"notes\":[],\"help\":\"declare it\",\"fixes\":[{\"file\":\"a.c\",\
+             \"line\":2,\"column\":5,\"end_line\":2,\"end_column\":6,\"replacement\":\"y\",\
+             \"applicable\":true}]}\n\
+             ]\n"
+        );
+    }
+
+    #[test]
+    fn sarif() {
+        let out = output(write_sarif);
+
+        assert!(out.contains("\"version\":\"2.1.0\""));
+        assert!(out.contains(
+            "{\"level\":\"error\",\"message\":{\"text\":\"undeclared \\\"x\\\"\"},\
+             \"locations\":[{\"physicalLocation\":{\"artifactLocation\":{\"uri\":\"a.c\"},\
+             \"region\":{\"startLine\":2,\"startColumn\":5,\"endLine\":2,\"endColumn\":6}}}]"
+        ));
+        assert!(out.contains("\"fixes\":[{\"description\":{\"text\":\"replace with `y`\"}"));
+    }
+}
@@ -0,0 +1,673 @@
+use std::fmt;
+use std::fs;
+use std::rc::Rc;
+
+use crate::diagnostic::Diagnostic;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TokenType {
+    End,
+
+    Type,
+    Const,
+    Struct,
+    For,
+    While,
+    Do,
+    If,
+    Else,
+    Break,
+    Continue,
+    Return,
+
+    Ident,
+    IntLit,
+    RealLit,
+    StrLit,
+    CharLit,
+
+    LPar,
+    RPar,
+    LBrak,
+    RBrak,
+    LBrace,
+    RBrace,
+
+    Dot,
+    Arrow,
+    Comma,
+    Semi,
+    Quest,
+    Colon,
+    Hash,
+    DHash,
+
+    Plus,
+    Minus,
+    Star,
+    Slash,
+    Mod,
+    Tilde,
+
+    Pipe,
+    Amp,
+    Caret,
+    Bang,
+    DPipe,
+    DAmp,
+    LShift,
+    RShift,
+
+    Assign,
+    PlusAssign,
+    MinusAssign,
+    StarAssign,
+    SlashAssign,
+    ModAssign,
+    PipeAssign,
+    AmpAssign,
+    CaretAssign,
+    LShiftAssign,
+    RShiftAssign,
+
+    Incr,
+    Decr,
+
+    Eq,
+    Ne,
+    Gt,
+    Ge,
+    Lt,
+    Le,
+}
+
+impl TokenType {
+    pub fn name(&self) -> &'static str {
+        match self {
+            TokenType::End => "END",
+            TokenType::Type => "TYPE",
+            TokenType::Const => "CONST",
+            TokenType::Struct => "STRUCT",
+            TokenType::For => "FOR",
+            TokenType::While => "WHILE",
+            TokenType::Do => "DO",
+            TokenType::If => "IF",
+            TokenType::Else => "ELSE",
+            TokenType::Break => "BREAK",
+            TokenType::Continue => "CONTINUE",
+            TokenType::Return => "RETURN",
+            TokenType::Ident => "IDENT",
+            TokenType::IntLit => "INT_LIT",
+            TokenType::RealLit => "REAL_LIT",
+            TokenType::StrLit => "STR_LIT",
+            TokenType::CharLit => "CHAR_LIT",
+            TokenType::LPar => "LPAR",
+            TokenType::RPar => "RPAR",
+            TokenType::LBrak => "LBRAK",
+            TokenType::RBrak => "RBRAK",
+            TokenType::LBrace => "LBRACE",
+            TokenType::RBrace => "RBRACE",
+            TokenType::Dot => "DOT",
+            TokenType::Arrow => "ARROW",
+            TokenType::Comma => "COMMA",
+            TokenType::Semi => "SEMI",
+            TokenType::Quest => "QUEST",
+            TokenType::Colon => "COLON",
+            TokenType::Hash => "HASH",
+            TokenType::DHash => "DHASH",
+            TokenType::Plus => "PLUS",
+            TokenType::Minus => "MINUS",
+            TokenType::Star => "STAR",
+            TokenType::Slash => "SLASH",
+            TokenType::Mod => "MOD",
+            TokenType::Tilde => "TILDE",
+            TokenType::Pipe => "PIPE",
+            TokenType::Amp => "AMP",
+            TokenType::Caret => "CARET",
+            TokenType::Bang => "BANG",
+            TokenType::DPipe => "DPIPE",
+            TokenType::DAmp => "DAMP",
+            TokenType::LShift => "LSHIFT",
+            TokenType::RShift => "RSHIFT",
+            TokenType::Assign => "ASSIGN",
+            TokenType::PlusAssign => "PLUSASSIGN",
+            TokenType::MinusAssign => "MINUSASSIGN",
+            TokenType::StarAssign => "STARASSIGN",
+            TokenType::SlashAssign => "SLASHASSIGN",
+            TokenType::ModAssign => "MODASSIGN",
+            TokenType::PipeAssign => "PIPEASSIGN",
+            TokenType::AmpAssign => "AMPASSIGN",
+            TokenType::CaretAssign => "CARETASSIGN",
+            TokenType::LShiftAssign => "LSHIFTASSIGN",
+            TokenType::RShiftAssign => "RSHIFTASSIGN",
+            TokenType::Incr => "INCR",
+            TokenType::Decr => "DECR",
+            TokenType::Eq => "EQ",
+            TokenType::Ne => "NE",
+            TokenType::Gt => "GT",
+            TokenType::Ge => "GE",
+            TokenType::Lt => "LT",
+            TokenType::Le => "LE",
+        }
+    }
+}
+
+impl fmt::Display for TokenType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Location of a piece of source text. `lineno` and `col` are 1-based and
+/// refer to the first character, `end_lineno` and `end_col` to the position
+/// just past the last one; `start` and `end` are byte offsets into the file
+/// named by `infile_name`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Span {
+    pub infile_name: Rc<str>,
+    pub lineno: usize,
+    pub col: usize,
+    pub end_lineno: usize,
+    pub end_col: usize,
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    /// Span covering `self` through `other`.
+    pub fn to(&self, other: &Span) -> Span {
+        let mut span = self.clone();
+
+        if self.infile_name == other.infile_name && other.end > span.end {
+            span.end = other.end;
+            span.end_lineno = other.end_lineno;
+            span.end_col = other.end_col;
+        }
+
+        span
+    }
+
+    /// Empty span just past the end of `self`, where text can be inserted.
+    pub fn after(&self) -> Span {
+        Span {
+            lineno: self.end_lineno,
+            col: self.end_col,
+            start: self.end,
+            ..self.clone()
+        }
+    }
+}
+
+/// One step of the macro expansion that produced a lexeme.
+#[derive(Debug, PartialEq)]
+pub struct Expansion {
+    pub name: String,
+    pub defined_at: Span,
+    pub invoked_at: Span,
+    /// Expansion the macro name itself was produced by, if any.
+    pub parent: Option<Rc<Expansion>>,
+}
+
+impl Expansion {
+    /// This expansion followed by the ones enclosing it, innermost first.
+    pub fn backtrace(&self) -> impl Iterator<Item = &Expansion> {
+        std::iter::successors(Some(self), |e| e.parent.as_deref())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Lexeme {
+    pub token: TokenType,
+    pub lex: String,
+    pub span: Span,
+    /// First token on its logical line; used to recognize directives.
+    pub line_start: bool,
+    pub expansion: Option<Rc<Expansion>>,
+}
+
+#[derive(Debug)]
+pub struct LexError {
+    pub span: Span,
+    pub code: &'static str,
+    pub message: String,
+}
+
+impl From<LexError> for Diagnostic {
+    fn from(err: LexError) -> Self {
+        Diagnostic::error(err.span, err.message).with_code(err.code)
+    }
+}
+
+pub struct Lexer<'a> {
+    infile_name: Rc<str>,
+    src: &'a [u8],
+    pos: usize,
+    lineno: usize,
+    col: usize,
+    line_start: bool,
+}
+
+impl<'a> Lexer<'a> {
+    pub fn new(infile_name: &str, src: &'a str) -> Self {
+        Lexer {
+            infile_name: infile_name.into(),
+            src: src.as_bytes(),
+            pos: 0,
+            lineno: 1,
+            col: 1,
+            line_start: true,
+        }
+    }
+
+    /// Reads `path` and tokenizes its contents.
+    pub fn lex_file(path: &str) -> Result<Vec<Lexeme>, LexError> {
+        let source = fs::read_to_string(path).map_err(|err| LexError {
+            span: Span {
+                infile_name: path.into(),
+                ..Span::default()
+            },
+            code: "Q0001",
+            message: format!("Couldn't open file for input: {}", err),
+        })?;
+
+        Lexer::new(path, &source).lex()
+    }
+
+    /// Tokenizes the whole input. The returned vector always ends with a
+    /// single `End` lexeme.
+    pub fn lex(mut self) -> Result<Vec<Lexeme>, LexError> {
+        let mut lexemes = Vec::new();
+
+        loop {
+            self.skip_whitespace_and_comments()?;
+
+            let start = self.mark();
+
+            if self.pos >= self.src.len() {
+                lexemes.push(self.lexeme(TokenType::End, start));
+                return Ok(lexemes);
+            }
+
+            let token = self.token(&start)?;
+            lexemes.push(self.lexeme(token, start));
+            self.line_start = false;
+        }
+    }
+
+    fn peek(&self) -> u8 {
+        self.peek_at(0)
+    }
+
+    fn peek_at(&self, offset: usize) -> u8 {
+        self.src.get(self.pos + offset).copied().unwrap_or(0)
+    }
+
+    fn bump(&mut self) -> u8 {
+        let c = self.peek();
+        self.pos += 1;
+
+        if c == b'\n' {
+            self.lineno += 1;
+            self.col = 1;
+            self.line_start = true;
+        } else {
+            self.col += 1;
+        }
+
+        c
+    }
+
+    fn eat(&mut self, c: u8) -> bool {
+        if self.peek() == c {
+            self.bump();
+            return true;
+        }
+
+        false
+    }
+
+    fn mark(&self) -> Span {
+        Span {
+            infile_name: self.infile_name.clone(),
+            lineno: self.lineno,
+            col: self.col,
+            end_lineno: self.lineno,
+            end_col: self.col,
+            start: self.pos,
+            end: self.pos,
+        }
+    }
+
+    fn lexeme(&self, token: TokenType, mut span: Span) -> Lexeme {
+        span.end = self.pos;
+        span.end_lineno = self.lineno;
+        span.end_col = self.col;
+
+        Lexeme {
+            token,
+            lex: String::from_utf8_lossy(&self.src[span.start..span.end]).into_owned(),
+            span,
+            line_start: self.line_start,
+            expansion: None,
+        }
+    }
+
+    fn error(&self, mut span: Span, code: &'static str, message: &str) -> LexError {
+        span.end = self.pos.max(span.start + 1).min(self.src.len());
+        span.end_lineno = span.lineno;
+        span.end_col = span.col + (span.end - span.start);
+
+        LexError {
+            span,
+            code,
+            message: message.to_string(),
+        }
+    }
+
+    fn skip_whitespace_and_comments(&mut self) -> Result<(), LexError> {
+        loop {
+            match (self.peek(), self.peek_at(1)) {
+                (b' ' | b'\t' | b'\r' | b'\n' | b'\x0b' | b'\x0c', _) => {
+                    self.bump();
+                }
+                // line continuation: the next line belongs to this one
+                (b'\\', b'\n') | (b'\\', b'\r') => {
+                    let line_start = self.line_start;
+                    self.bump();
+                    self.eat(b'\r');
+                    self.eat(b'\n');
+                    self.line_start = line_start;
+                }
+                (b'/', b'/') => {
+                    while self.pos < self.src.len() && self.peek() != b'\n' {
+                        self.bump();
+                    }
+                }
+                (b'/', b'*') => {
+                    let start = self.mark();
+                    self.bump();
+                    self.bump();
+
+                    loop {
+                        if self.pos >= self.src.len() {
+                            return Err(self.error(start, "Q0002", "Unclosed comment"));
+                        }
+
+                        if self.peek() == b'*' && self.peek_at(1) == b'/' {
+                            self.bump();
+                            self.bump();
+                            break;
+                        }
+
+                        self.bump();
+                    }
+                }
+                _ => return Ok(()),
+            }
+        }
+    }
+
+    fn token(&mut self, start: &Span) -> Result<TokenType, LexError> {
+        let c = self.peek();
+
+        if c.is_ascii_alphabetic() || c == b'_' {
+            return Ok(self.word(start));
+        }
+
+        if c.is_ascii_digit() || (c == b'.' && self.peek_at(1).is_ascii_digit()) {
+            return self.number(start);
+        }
+
+        if c == b'"' || c == b'\'' {
+            return self.quoted(start, c);
+        }
+
+        self.bump();
+
+        let token = match c {
+            b'(' => TokenType::LPar,
+            b')' => TokenType::RPar,
+            b'[' => TokenType::LBrak,
+            b']' => TokenType::RBrak,
+            b'{' => TokenType::LBrace,
+            b'}' => TokenType::RBrace,
+            b'.' => TokenType::Dot,
+            b',' => TokenType::Comma,
+            b';' => TokenType::Semi,
+            b'?' => TokenType::Quest,
+            b':' => TokenType::Colon,
+            b'#' if self.eat(b'#') => TokenType::DHash,
+            b'#' => TokenType::Hash,
+            b'~' => TokenType::Tilde,
+            b'%' if self.eat(b'=') => TokenType::ModAssign,
+            b'%' => TokenType::Mod,
+            b'^' if self.eat(b'=') => TokenType::CaretAssign,
+            b'^' => TokenType::Caret,
+            b'+' if self.eat(b'+') => TokenType::Incr,
+            b'+' if self.eat(b'=') => TokenType::PlusAssign,
+            b'+' => TokenType::Plus,
+            b'-' if self.eat(b'-') => TokenType::Decr,
+            b'-' if self.eat(b'=') => TokenType::MinusAssign,
+            b'-' if self.eat(b'>') => TokenType::Arrow,
+            b'-' => TokenType::Minus,
+            b'*' if self.eat(b'=') => TokenType::StarAssign,
+            b'*' => TokenType::Star,
+            b'/' if self.eat(b'=') => TokenType::SlashAssign,
+            b'/' => TokenType::Slash,
+            b'|' if self.eat(b'|') => TokenType::DPipe,
+            b'|' if self.eat(b'=') => TokenType::PipeAssign,
+            b'|' => TokenType::Pipe,
+            b'&' if self.eat(b'&') => TokenType::DAmp,
+            b'&' if self.eat(b'=') => TokenType::AmpAssign,
+            b'&' => TokenType::Amp,
+            b'!' if self.eat(b'=') => TokenType::Ne,
+            b'!' => TokenType::Bang,
+            b'=' if self.eat(b'=') => TokenType::Eq,
+            b'=' => TokenType::Assign,
+            b'<' if self.eat(b'<') => {
+                if self.eat(b'=') {
+                    TokenType::LShiftAssign
+                } else {
+                    TokenType::LShift
+                }
+            }
+            b'<' if self.eat(b'=') => TokenType::Le,
+            b'<' => TokenType::Lt,
+            b'>' if self.eat(b'>') => {
+                if self.eat(b'=') {
+                    TokenType::RShiftAssign
+                } else {
+                    TokenType::RShift
+                }
+            }
+            b'>' if self.eat(b'=') => TokenType::Ge,
+            b'>' => TokenType::Gt,
+            _ => {
+                // consume the rest of a multi-byte character so the error
+                // text is printable
+                while self.peek() & 0xc0 == 0x80 {
+                    self.bump();
+                }
+
+                return Err(self.error(start.clone(), "Q0003", "Unexpected character"));
+            }
+        };
+
+        Ok(token)
+    }
+
+    fn word(&mut self, start: &Span) -> TokenType {
+        while self.peek().is_ascii_alphanumeric() || self.peek() == b'_' {
+            self.bump();
+        }
+
+        match &self.src[start.start..self.pos] {
+            b"void" | b"char" | b"int" | b"float" => TokenType::Type,
+            b"const" => TokenType::Const,
+            b"struct" => TokenType::Struct,
+            b"for" => TokenType::For,
+            b"while" => TokenType::While,
+            b"do" => TokenType::Do,
+            b"if" => TokenType::If,
+            b"else" => TokenType::Else,
+            b"break" => TokenType::Break,
+            b"continue" => TokenType::Continue,
+            b"return" => TokenType::Return,
+            _ => TokenType::Ident,
+        }
+    }
+
+    fn number(&mut self, start: &Span) -> Result<TokenType, LexError> {
+        let mut token = TokenType::IntLit;
+
+        if self.peek() == b'0' && matches!(self.peek_at(1), b'x' | b'X') {
+            self.bump();
+            self.bump();
+
+            if !self.peek().is_ascii_hexdigit() {
+                return Err(self.error(start.clone(), "Q0004", "Malformed hexadecimal literal"));
+            }
+
+            while self.peek().is_ascii_hexdigit() {
+                self.bump();
+            }
+        } else {
+            while self.peek().is_ascii_digit() {
+                self.bump();
+            }
+
+            if self.peek() == b'.' {
+                token = TokenType::RealLit;
+                self.bump();
+
+                while self.peek().is_ascii_digit() {
+                    self.bump();
+                }
+            }
+
+            if matches!(self.peek(), b'e' | b'E') {
+                let sign = matches!(self.peek_at(1), b'+' | b'-') as usize;
+
+                if self.peek_at(1 + sign).is_ascii_digit() {
+                    token = TokenType::RealLit;
+
+                    for _ in 0..=sign {
+                        self.bump();
+                    }
+
+                    while self.peek().is_ascii_digit() {
+                        self.bump();
+                    }
+                }
+            }
+        }
+
+        if self.peek().is_ascii_alphanumeric() || self.peek() == b'_' {
+            while self.peek().is_ascii_alphanumeric() || self.peek() == b'_' {
+                self.bump();
+            }
+
+            return Err(self.error(start.clone(), "Q0004", "Malformed numeric literal"));
+        }
+
+        Ok(token)
+    }
+
+    fn quoted(&mut self, start: &Span, quote: u8) -> Result<TokenType, LexError> {
+        self.bump();
+
+        loop {
+            match self.peek() {
+                c if c == quote => {
+                    self.bump();
+                    break;
+                }
+                b'\\' => {
+                    self.bump();
+
+                    if self.pos < self.src.len() && self.peek() != b'\n' {
+                        self.bump();
+                    }
+                }
+                c if c == b'\n' || self.pos >= self.src.len() => {
+                    let message = if quote == b'"' {
+                        "Unclosed string literal"
+                    } else {
+                        "Unclosed character literal"
+                    };
+
+                    return Err(self.error(start.clone(), "Q0005", message));
+                }
+                _ => {
+                    self.bump();
+                }
+            }
+        }
+
+        if quote == b'"' {
+            return Ok(TokenType::StrLit);
+        }
+
+        if self.pos - start.start == 2 {
+            return Err(self.error(start.clone(), "Q0006", "Empty character literal"));
+        }
+
+        Ok(TokenType::CharLit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lex(src: &str) -> Vec<Lexeme> {
+        Lexer::new("test.c", src).lex().expect("input lexes")
+    }
+
+    fn tokens(src: &str) -> Vec<TokenType> {
+        lex(src).into_iter().map(|l| l.token).collect()
+    }
+
+    fn error_code(src: &str) -> &'static str {
+        Lexer::new("test.c", src)
+            .lex()
+            .expect_err("input is invalid")
+            .code
+    }
+
+    #[test]
+    fn declaration() {
+        use TokenType::*;
+
+        assert_eq!(
+            tokens("int x = 0x1F;"),
+            [Type, Ident, Assign, IntLit, Semi, End]
+        );
+    }
+
+    #[test]
+    fn comments_are_skipped() {
+        let lexemes = lex("a /* b */ c // d\ne");
+        let words: Vec<&str> = lexemes.iter().map(|l| l.lex.as_str()).collect();
+
+        assert_eq!(words, ["a", "c", "e", ""]);
+    }
+
+    #[test]
+    fn spans() {
+        let lexemes = lex("int\n  count;");
+        let span = &lexemes[1].span;
+
+        assert_eq!((span.lineno, span.col), (2, 3));
+        assert_eq!((span.start, span.end), (6, 11));
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(error_code("/* open"), "Q0002");
+        assert_eq!(error_code("int @;"), "Q0003");
+        assert_eq!(error_code("0x;"), "Q0004");
+        assert_eq!(error_code("\"open\n\""), "Q0005");
+        assert_eq!(error_code("''"), "Q0006");
+    }
+}
@@ -1,4 +1,4 @@
-use log::{Record, Level, Metadata};
+use log::{Level, Metadata, Record};
 
 pub struct Logger;
 
@@ -9,7 +9,7 @@ impl log::Log for Logger {
 
     fn log(&self, record: &Record) {
         if self.enabled(record.metadata()) {
-            println!("[{}] {}", record.level(), record.args());
+            eprintln!("[{}] {}", record.level(), record.args());
         }
     }
 
@@ -1,29 +1,236 @@
+mod cfg;
+mod checker;
+mod diagnostic;
+mod explain;
+mod interpreter;
+mod json;
 mod lexer;
+mod listing;
 mod parser;
+mod preprocessor;
+mod runtime;
+mod suggest;
+mod warning;
 // mod server;
 mod logger;
 
+use std::env;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, IsTerminal, Write};
 use std::process::exit;
+use std::thread;
 
-use clap::Parser;
-use log::{info, Level, LevelFilter, SetLoggerError};
+use checker::Checker;
+use clap::{Parser, Subcommand, ValueEnum};
+use diagnostic::{Diagnostic, Fix, Renderer};
+use interpreter::Interpreter;
+use lexer::{Lexeme, Span, TokenType};
+use log::{debug, LevelFilter};
 use logger::Logger;
+use preprocessor::Preprocessor;
+use runtime::Runtime;
+use warning::{Suppression, WarningOptions};
 
 const PKG_NAME: &str = env!("CARGO_PKG_NAME");
 const PKG_VERSION: &str = env!("CARGO_PKG_VERSION");
 
 static LOGGER: Logger = Logger;
 
+/// The parser, checker and interpreter recurse over the program, so they
+/// run on a thread with room for deep nesting.
+const STACK_SIZE: usize = 1 << 30;
+
 /// Simple program to greet a person
 #[derive(Parser, Debug)]
-#[command(version, about, long_about = None)]
+#[command(version, about, long_about = None, subcommand_negates_reqs = true)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    #[arg(short, long, required_unless_present = "explain")]
+    input_file: Option<String>,
+
+    /// Describe a diagnostic code, such as Q0102, with an example
+    #[arg(long, value_name = "CODE")]
+    explain: Option<String>,
+
+    /// Only run the lexer and list every token
+    #[arg(short, long)]
+    lex: bool,
+
+    /// Write listings to this file instead of standard output
+    #[arg(short, long)]
+    output_file: Option<String>,
+
+    /// List every declared name: variables, functions, parameters, structs
+    /// and members
+    #[arg(short, long, conflicts_with = "types")]
+    declarations: bool,
+
+    /// List the type of each expression statement in a function body
     #[arg(short, long)]
-    input_file: String,
+    types: bool,
+
+    /// With --types, list every expression and subexpression, with columns
+    #[arg(long, requires = "types")]
+    verbose: bool,
+
+    /// Header declaring further runtime library functions; may be repeated
+    #[arg(long, value_name = "HEADER", global = true)]
+    runtime: Vec<String>,
+
+    /// Stop after this many errors (0 for no limit)
+    #[arg(long, default_value_t = 20, global = true)]
+    error_limit: usize,
+
+    /// How to report errors and warnings. The machine-readable formats are
+    /// written to standard output once compilation stops, so listings
+    /// should go to a file with --output-file
+    #[arg(long, value_enum, default_value_t = DiagnosticsFormat::Text, global = true)]
+    diagnostics_format: DiagnosticsFormat,
+
+    /// Control a warning: NAME or no-NAME to enable or disable it,
+    /// error=NAME to make it an error, error to make every warning an
+    /// error, all to enable every warning; may be repeated
+    #[arg(short = 'W', value_name = "WARNING", global = true)]
+    warnings: Vec<String>,
+
+    /// Rewrite the input file with every fix that is certain enough to make
+    /// without checking, such as a missing ';' at the end of a line
+    #[arg(long, global = true)]
+    apply_fixes: bool,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Check a program and run it with the interpreter
+    ///
+    /// The runtime library reads standard input and writes standard output,
+    /// and the exit status is what main returns.
+    Run { file: String },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum DiagnosticsFormat {
+    /// Source snippets on standard error, as they are found
+    Text,
+    /// A JSON array with one object per diagnostic
+    Json,
+    /// A SARIF 2.1.0 log
+    Sarif,
+}
+
+/// Sends diagnostics out in the chosen format, after dropping or promoting
+/// warnings as the `-W` options and pragmas say. Text is printed right away;
+/// the other formats are collected and written as one document on exit.
+struct Reporter {
+    format: DiagnosticsFormat,
+    renderer: Renderer,
+    warnings: WarningOptions,
+    suppressions: Vec<Suppression>,
+    diagnostics: Vec<Diagnostic>,
+    /// Whether any error has been reported.
+    failed: bool,
+    /// With --apply-fixes, the input file and the fixes to make to it.
+    fix_file: Option<String>,
+    fixes: Vec<Fix>,
+    /// Under `quark run`, standard output belongs to the program, so a JSON
+    /// or SARIF document is written to standard error instead.
+    run: bool,
+}
+
+impl Reporter {
+    fn report(&mut self, diagnostics: &[Diagnostic]) {
+        let diagnostics: Vec<Diagnostic> = diagnostics
+            .iter()
+            .filter_map(|d| self.warnings.apply(d, &self.suppressions))
+            .collect();
+
+        self.failed |= diagnostics.iter().any(Diagnostic::is_error);
+
+        if let Some(file) = &self.fix_file {
+            let fixes = diagnostics.iter().flat_map(|d| &d.fixes);
+            self.fixes.extend(
+                fixes
+                    .filter(|f| f.applicable && &*f.span.infile_name == file)
+                    .cloned(),
+            );
+        }
+
+        if self.format == DiagnosticsFormat::Text {
+            for d in &diagnostics {
+                eprintln!("{}", self.renderer.render(d));
+            }
+        } else {
+            self.diagnostics.extend(diagnostics);
+        }
+    }
+
+    fn exit(&self, code: i32) -> ! {
+        let is_text = self.format == DiagnosticsFormat::Text;
+
+        if let Some(file) = &self.fix_file {
+            let applied = write_fixes(file, &self.fixes);
+
+            // a document on standard error must be all there is on it
+            if applied > 0 && (is_text || !self.run) {
+                let plural = if applied == 1 { "" } else { "es" };
+                eprintln!("Applied {} fix{} to {}", applied, plural, file);
+            }
+        }
+
+        let write = |out: &mut dyn Write| match self.format {
+            DiagnosticsFormat::Text => Ok(()),
+            DiagnosticsFormat::Json => json::write_json(out, &self.diagnostics),
+            DiagnosticsFormat::Sarif => json::write_sarif(out, &self.diagnostics),
+        };
+
+        if !is_text {
+            if self.run {
+                write_to(io::stderr().lock(), write);
+            } else {
+                write_listing(None, write);
+            }
+        }
+
+        exit(code)
+    }
 }
 
 fn main() {
     let args = Args::parse();
+
+    let compiler = thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(move || compile(args))
+        .expect("failed to start the compiler thread");
+
+    // a panic has already been reported by the thread
+    if compiler.join().is_err() {
+        exit(101);
+    }
+}
+
+fn compile(args: Args) {
+    if let Some(code) = &args.explain {
+        match explain::explanation(code) {
+            Some(text) => print!("{}", text),
+            None => {
+                eprintln!("No explanation for {}", code);
+                exit(1);
+            }
+        }
+
+        return;
+    }
+
+    let input_file = match &args.command {
+        Some(Command::Run { file }) => file,
+        None => args
+            .input_file
+            .as_deref()
+            .expect("required without --explain"),
+    };
     let log_level = "info";
     let log_level = match log_level.to_lowercase().as_str() {
         "error" => LevelFilter::Error,
@@ -41,6 +248,224 @@ fn main() {
         }
     }
 
-    info!("{}", PKG_NAME);
-    info!("Version: {}", PKG_VERSION);
+    debug!("{}", PKG_NAME);
+    debug!("Version: {}", PKG_VERSION);
+
+    let color = io::stderr().is_terminal() && env::var_os("NO_COLOR").is_none();
+    let mut renderer = Renderer::new(color);
+    renderer.add_source(runtime::BUNDLED_NAME, runtime::BUNDLED);
+
+    let mut warnings = WarningOptions::default();
+
+    for option in &args.warnings {
+        if let Err(err) = warnings.set(option) {
+            eprintln!("{}", err);
+            exit(1);
+        }
+    }
+
+    let mut reporter = Reporter {
+        format: args.diagnostics_format,
+        renderer,
+        warnings,
+        suppressions: Vec::new(),
+        diagnostics: Vec::new(),
+        failed: false,
+        fix_file: args.apply_fixes.then(|| input_file.to_string()),
+        fixes: Vec::new(),
+        run: args.command.is_some(),
+    };
+
+    let mut preprocessor = Preprocessor::new();
+    let result = preprocessor.preprocess_file(input_file);
+    reporter.suppressions = preprocessor.suppressions().to_vec();
+    reporter.report(preprocessor.warnings());
+
+    let lexemes = match result {
+        Ok(lexemes) => lexemes,
+        Err(err) => {
+            reporter.report(&[err.into()]);
+            reporter.exit(1);
+        }
+    };
+
+    debug!("{} lexemes read from {}", lexemes.len(), input_file);
+
+    if args.lex {
+        write_listing(args.output_file.as_deref(), |out| {
+            write_lexemes(out, &lexemes)
+        });
+        reporter.exit(0);
+    }
+
+    let (program, errors) = parser::Parser::new(lexemes)
+        .error_limit(args.error_limit)
+        .parse();
+
+    if !errors.is_empty() {
+        let mut errors: Vec<Diagnostic> = errors.into_iter().map(Diagnostic::from).collect();
+        let stopped = args.error_limit != 0 && errors.len() >= args.error_limit;
+
+        // a JSON or SARIF document must be all there is on its stream
+        let is_text = args.diagnostics_format == DiagnosticsFormat::Text;
+
+        if stopped && !is_text {
+            if let Some(last) = errors.pop() {
+                errors.push(last.with_note("too many errors, stopping now"));
+            }
+        }
+
+        reporter.report(&errors);
+
+        if stopped && is_text {
+            eprintln!("Too many errors, stopping now");
+        }
+
+        reporter.exit(1);
+    }
+
+    debug!("{} top-level declarations", program.decls.len());
+
+    let mut runtime = Runtime::bundled();
+
+    for path in &args.runtime {
+        if let Err(errors) = runtime.load(path) {
+            reporter.report(&errors);
+            reporter.exit(1);
+        }
+    }
+
+    let diagnostics = Checker::check(&program, &runtime);
+    reporter.report(&diagnostics);
+
+    if args.declarations {
+        write_listing(args.output_file.as_deref(), |out| {
+            listing::write_declarations(out, &program)
+        });
+    }
+
+    if args.types {
+        write_listing(args.output_file.as_deref(), |out| {
+            if args.verbose {
+                listing::write_types_verbose(out, &program)
+            } else {
+                listing::write_types(out, &program)
+            }
+        });
+    }
+
+    if reporter.failed || args.command.is_none() {
+        reporter.exit(if reporter.failed { 1 } else { 0 });
+    }
+
+    let output = BufWriter::new(io::stdout().lock());
+
+    match Interpreter::new(io::stdin().lock(), output).run(&program) {
+        Ok(Some(status)) => reporter.exit(status),
+        Ok(None) => {
+            let span = Span {
+                infile_name: input_file.into(),
+                ..Span::default()
+            };
+            reporter.report(&[Diagnostic::error(span, "no main function")]);
+            reporter.exit(1);
+        }
+        Err(err) => {
+            reporter.report(&[err.into()]);
+            reporter.exit(1);
+        }
+    }
+}
+
+/// Rewrites `path` with `fixes` made to it, returning how many were made.
+fn write_fixes(path: &str, fixes: &[Fix]) -> usize {
+    if fixes.is_empty() {
+        return 0;
+    }
+
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(err) => {
+            eprintln!("Couldn't read {}: {}", path, err);
+            exit(1);
+        }
+    };
+
+    let (text, applied) = diagnostic::apply_fixes(&text, fixes);
+
+    if applied > 0 {
+        if let Err(err) = fs::write(path, text) {
+            eprintln!("Couldn't write {}: {}", path, err);
+            exit(1);
+        }
+    }
+
+    applied
+}
+
+/// Runs `write` on the file at `path`, or on standard output if there is
+/// none, exiting on failure.
+fn write_listing(path: Option<&str>, write: impl FnOnce(&mut dyn Write) -> io::Result<()>) {
+    let out: Box<dyn Write> = match path {
+        Some(path) => match File::create(path) {
+            Ok(file) => Box::new(file),
+            Err(err) => {
+                eprintln!("Couldn't open file for output: {}: {}", path, err);
+                exit(1);
+            }
+        },
+        None => Box::new(io::stdout().lock()),
+    };
+
+    write_to(out, write);
+}
+
+/// Runs `write` on `out`, exiting on failure.
+fn write_to(out: impl Write, write: impl FnOnce(&mut dyn Write) -> io::Result<()>) {
+    let mut out = BufWriter::new(out);
+
+    if let Err(err) = write(&mut out).and_then(|()| out.flush()) {
+        eprintln!("Couldn't write output: {}", err);
+        exit(1);
+    }
+}
+
+fn write_lexemes(out: &mut dyn Write, lexemes: &[Lexeme]) -> io::Result<()> {
+    for l in lexemes {
+        if l.token == TokenType::End {
+            break;
+        }
+
+        writeln!(
+            out,
+            "File {} Line {} Token {} Text {}",
+            l.span.infile_name, l.span.lineno, l.token, l.lex
+        )?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::Lexer;
+
+    #[test]
+    fn lexeme_listing() {
+        let lexemes = Lexer::new("test.c", "int x;\nx = 1;").lex().unwrap();
+        let mut out = Vec::new();
+        write_lexemes(&mut out, &lexemes).unwrap();
+
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "File test.c Line 1 Token TYPE Text int\n\
+             File test.c Line 1 Token IDENT Text x\n\
+             File test.c Line 1 Token SEMI Text ;\n\
+             File test.c Line 2 Token IDENT Text x\n\
+             File test.c Line 2 Token ASSIGN Text =\n\
+             File test.c Line 2 Token INT_LIT Text 1\n\
+             File test.c Line 2 Token SEMI Text ;\n"
+        );
+    }
 }
@@ -0,0 +1,1927 @@
+use std::cell::OnceCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
+
+use crate::diagnostic::{Diagnostic, Fix};
+use crate::lexer::{Expansion, Lexeme, Span, TokenType};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Type {
+    Void,
+    Char,
+    Int,
+    Float,
+    /// Name and the definition it refers to, numbered in the order the
+    /// definitions appear; `None` when no definition is in scope. Two
+    /// structs with the same name in different blocks are different types.
+    Struct(String, Option<usize>),
+    /// Element type and length; `int m[2][3]` is an array of two `int[3]`.
+    /// The length is left out for array parameters.
+    Array(Box<Type>, Option<usize>),
+    /// Type of an expression the checker has already reported an error for.
+    Error,
+}
+
+impl Type {
+    pub fn is_integer(&self) -> bool {
+        matches!(self, Type::Char | Type::Int)
+    }
+
+    pub fn is_arithmetic(&self) -> bool {
+        matches!(self, Type::Char | Type::Int | Type::Float)
+    }
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Type::Void => f.write_str("void"),
+            Type::Char => f.write_str("char"),
+            Type::Int => f.write_str("int"),
+            Type::Float => f.write_str("float"),
+            Type::Struct(name, _) => write!(f, "struct {}", name),
+            Type::Array(..) => {
+                // `int m[2][3]` is written `int[2][3]`, outermost length first
+                let mut elem = self;
+                let mut dims = String::new();
+
+                while let Type::Array(inner, len) = elem {
+                    match len {
+                        Some(len) => dims.push_str(&format!("[{}]", len)),
+                        None => dims.push_str("[]"),
+                    }
+                    elem = inner;
+                }
+
+                write!(f, "{}{}", elem, dims)
+            }
+            Type::Error => f.write_str("error"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UnaryOp {
+    Neg,
+    Plus,
+    Not,
+    BitNot,
+    AddrOf,
+    Deref,
+}
+
+impl fmt::Display for UnaryOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            UnaryOp::Neg => "-",
+            UnaryOp::Plus => "+",
+            UnaryOp::Not => "!",
+            UnaryOp::BitNot => "~",
+            UnaryOp::AddrOf => "&",
+            UnaryOp::Deref => "*",
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IncDecOp {
+    PreIncr,
+    PreDecr,
+    PostIncr,
+    PostDecr,
+}
+
+impl fmt::Display for IncDecOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            IncDecOp::PreIncr | IncDecOp::PostIncr => "++",
+            IncDecOp::PreDecr | IncDecOp::PostDecr => "--",
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BinaryOp {
+    LogOr,
+    LogAnd,
+    BitOr,
+    BitXor,
+    BitAnd,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Shl,
+    Shr,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+}
+
+impl fmt::Display for BinaryOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            BinaryOp::LogOr => "||",
+            BinaryOp::LogAnd => "&&",
+            BinaryOp::BitOr => "|",
+            BinaryOp::BitXor => "^",
+            BinaryOp::BitAnd => "&",
+            BinaryOp::Eq => "==",
+            BinaryOp::Ne => "!=",
+            BinaryOp::Lt => "<",
+            BinaryOp::Le => "<=",
+            BinaryOp::Gt => ">",
+            BinaryOp::Ge => ">=",
+            BinaryOp::Shl => "<<",
+            BinaryOp::Shr => ">>",
+            BinaryOp::Add => "+",
+            BinaryOp::Sub => "-",
+            BinaryOp::Mul => "*",
+            BinaryOp::Div => "/",
+            BinaryOp::Mod => "%",
+        })
+    }
+}
+
+impl BinaryOp {
+    /// Operators whose operands must have integer type.
+    pub fn is_integer_only(&self) -> bool {
+        matches!(
+            self,
+            BinaryOp::Mod
+                | BinaryOp::BitOr
+                | BinaryOp::BitXor
+                | BinaryOp::BitAnd
+                | BinaryOp::Shl
+                | BinaryOp::Shr
+        )
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Expr {
+    pub kind: ExprKind,
+    pub span: Span,
+    /// Set by the checker.
+    pub ty: OnceCell<Type>,
+    /// The macro expansion the expression's operator, or its only token,
+    /// came from.
+    pub expansion: Option<Rc<Expansion>>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ExprKind {
+    IntLit(i64),
+    RealLit(f64),
+    CharLit(u8),
+    /// Contents of a string literal with escapes resolved.
+    StrLit(Vec<u8>),
+    Ident(String),
+    Unary {
+        op: UnaryOp,
+        operand: Box<Expr>,
+    },
+    IncDec {
+        op: IncDecOp,
+        operand: Box<Expr>,
+    },
+    Binary {
+        op: BinaryOp,
+        lhs: Box<Expr>,
+        rhs: Box<Expr>,
+    },
+    /// `lhs = rhs`, or a compound assignment such as `lhs += rhs` when `op`
+    /// is set.
+    Assign {
+        op: Option<BinaryOp>,
+        lhs: Box<Expr>,
+        rhs: Box<Expr>,
+    },
+    Ternary {
+        cond: Box<Expr>,
+        then: Box<Expr>,
+        els: Box<Expr>,
+    },
+    Cast {
+        ty: Type,
+        expr: Box<Expr>,
+    },
+    Call {
+        name: String,
+        name_span: Span,
+        args: Vec<Expr>,
+    },
+    Index {
+        array: Box<Expr>,
+        index: Box<Expr>,
+    },
+    Member {
+        base: Box<Expr>,
+        member: String,
+        member_span: Span,
+    },
+}
+
+/// Initial value of a variable: an expression, or a brace-enclosed list for
+/// an array or struct.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Initializer {
+    Expr(Expr),
+    List(Vec<Initializer>, Span),
+}
+
+impl Initializer {
+    pub fn span(&self) -> &Span {
+        match self {
+            Initializer::Expr(e) => &e.span,
+            Initializer::List(_, span) => span,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct VarDecl {
+    pub ty: Type,
+    pub name: String,
+    pub is_const: bool,
+    pub init: Option<Initializer>,
+    /// Span of the declared name.
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Block {
+    pub stmts: Vec<Stmt>,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct If {
+    pub cond: Expr,
+    pub then: Box<Stmt>,
+    pub els: Option<Box<Stmt>>,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct For {
+    /// An expression statement or a variable declaration.
+    pub init: Option<Box<Stmt>>,
+    pub cond: Option<Expr>,
+    pub step: Option<Expr>,
+    pub body: Box<Stmt>,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct While {
+    pub cond: Expr,
+    pub body: Box<Stmt>,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct DoWhile {
+    pub body: Box<Stmt>,
+    pub cond: Expr,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Return {
+    pub value: Option<Expr>,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Stmt {
+    Expr(Expr),
+    /// Local variable declarations; `int a, b[3];` declares two.
+    Var(Vec<VarDecl>),
+    Struct(StructDef),
+    Block(Block),
+    If(If),
+    For(Box<For>),
+    While(While),
+    DoWhile(DoWhile),
+    Return(Return),
+    Break(Span),
+    Continue(Span),
+    Empty(Span),
+}
+
+impl Stmt {
+    /// The whole statement, or the first declared name for declarations.
+    pub fn span(&self) -> &Span {
+        match self {
+            Stmt::Expr(e) => &e.span,
+            Stmt::Var(vars) => &vars[0].span,
+            Stmt::Struct(def) => &def.span,
+            Stmt::Block(b) => &b.span,
+            Stmt::If(s) => &s.span,
+            Stmt::For(s) => &s.span,
+            Stmt::While(s) => &s.span,
+            Stmt::DoWhile(s) => &s.span,
+            Stmt::Return(r) => &r.span,
+            Stmt::Break(span) | Stmt::Continue(span) | Stmt::Empty(span) => span,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct StructDef {
+    /// Number referred to by `Type::Struct`.
+    pub id: usize,
+    pub name: String,
+    pub members: Vec<VarDecl>,
+    /// Span of the struct name.
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct FunctionDef {
+    pub return_type: Type,
+    pub name: String,
+    /// Parameters; a prototype may leave their names empty.
+    pub params: Vec<VarDecl>,
+    /// `None` for a prototype.
+    pub body: Option<Block>,
+    /// Span of the function name.
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Decl {
+    Function(FunctionDef),
+    Struct(StructDef),
+    /// Global variable declarations sharing one type specifier.
+    Var(Vec<VarDecl>),
+}
+
+/// A whole translation unit, in source order.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Program {
+    pub decls: Vec<Decl>,
+}
+
+#[derive(Clone, Debug)]
+pub struct ParseError {
+    /// Boxed to keep `Result`s small.
+    pub lexeme: Box<Lexeme>,
+    pub code: &'static str,
+    pub message: String,
+    /// Boxed for the same reason.
+    pub fix: Option<Box<Fix>>,
+}
+
+impl From<ParseError> for Diagnostic {
+    fn from(err: ParseError) -> Self {
+        let d = Diagnostic::error(err.lexeme.span.clone(), err.message)
+            .with_code(err.code)
+            .with_expansion(err.lexeme.expansion.as_ref());
+
+        match err.fix {
+            Some(fix) => d
+                .with_label(
+                    fix.span.clone(),
+                    format!("insert `{}` here", fix.replacement),
+                )
+                .with_fix(fix.span, fix.replacement, fix.applicable),
+            None => d,
+        }
+    }
+}
+
+type Result<T> = std::result::Result<T, ParseError>;
+
+/// Recursive-descent parser over the preprocessed lexeme stream.
+///
+/// Binary operators are parsed with one function per precedence level, from
+/// `ternary` (loosest) down to `product`, then unary, postfix and primary
+/// expressions.
+///
+/// A syntax error abandons the statement or declaration it occurs in; the
+/// error is recorded and parsing resumes at the next `;`, `}` or type
+/// keyword, so one run reports every error in the file.
+pub struct Parser {
+    lexemes: Vec<Lexeme>,
+    pos: usize,
+    errors: Vec<ParseError>,
+    /// Give up after this many errors; 0 means no limit.
+    error_limit: usize,
+    /// Set once the error limit is hit; every caller then unwinds.
+    stopped: bool,
+    /// Struct names visible at the current point, innermost block last.
+    struct_scopes: Vec<HashMap<String, usize>>,
+    struct_count: usize,
+}
+
+impl Parser {
+    /// `lexemes` must end with an `End` lexeme, as produced by the lexer.
+    pub fn new(lexemes: Vec<Lexeme>) -> Self {
+        Parser {
+            lexemes,
+            pos: 0,
+            errors: Vec::new(),
+            error_limit: 0,
+            stopped: false,
+            struct_scopes: vec![HashMap::new()],
+            struct_count: 0,
+        }
+    }
+
+    pub fn error_limit(mut self, limit: usize) -> Self {
+        self.error_limit = limit;
+        self
+    }
+
+    /// Parses the whole input, returning whatever could be parsed along with
+    /// every syntax error found.
+    pub fn parse(mut self) -> (Program, Vec<ParseError>) {
+        let mut program = Program::default();
+
+        while self.peek().token != TokenType::End {
+            let start = self.pos;
+
+            match self.declaration() {
+                Ok(decls) => program.decls.extend(decls),
+                Err(err) => {
+                    if self.recover(err).is_err() {
+                        break;
+                    }
+
+                    // a stray '}' is where statement-level recovery stops,
+                    // so make sure the top level moves past it
+                    if self.pos == start {
+                        self.bump();
+                    }
+                }
+            }
+        }
+
+        (program, self.errors)
+    }
+
+    fn peek(&self) -> &Lexeme {
+        &self.lexemes[self.pos]
+    }
+
+    fn peek_at(&self, offset: usize) -> &Lexeme {
+        let i = (self.pos + offset).min(self.lexemes.len() - 1);
+        &self.lexemes[i]
+    }
+
+    fn bump(&mut self) -> Lexeme {
+        let l = self.lexemes[self.pos].clone();
+
+        if l.token != TokenType::End {
+            self.pos += 1;
+        }
+
+        l
+    }
+
+    fn eat(&mut self, token: TokenType) -> bool {
+        if self.peek().token == token {
+            self.bump();
+            return true;
+        }
+
+        false
+    }
+
+    /// Span of the most recently consumed lexeme.
+    fn prev_span(&self) -> &Span {
+        &self.lexemes[self.pos.saturating_sub(1)].span
+    }
+
+    fn error<T>(&self, code: &'static str, message: impl Into<String>) -> Result<T> {
+        Err(ParseError {
+            lexeme: Box::new(self.peek().clone()),
+            code,
+            message: message.into(),
+            fix: None,
+        })
+    }
+
+    /// Records `err` and skips ahead to where parsing can resume. Fails once
+    /// the error limit is reached, so that every enclosing construct unwinds.
+    fn recover(&mut self, err: ParseError) -> Result<()> {
+        if self.stopped {
+            return Err(err);
+        }
+
+        // errors reported at the same token are almost always a cascade
+        if self
+            .errors
+            .last()
+            .is_none_or(|e| e.lexeme.span != err.lexeme.span)
+        {
+            self.errors.push(err.clone());
+        }
+
+        if self.error_limit != 0 && self.errors.len() >= self.error_limit {
+            self.stopped = true;
+            return Err(err);
+        }
+
+        self.synchronize();
+
+        Ok(())
+    }
+
+    /// Skips to just past the next `;`, or to the next `}` or type keyword,
+    /// stepping over complete `{ ... }` groups on the way.
+    fn synchronize(&mut self) {
+        let mut depth = 0;
+
+        loop {
+            match self.peek().token {
+                TokenType::End => return,
+                TokenType::Semi if depth == 0 => {
+                    self.bump();
+                    return;
+                }
+                TokenType::RBrace if depth == 0 => return,
+                TokenType::Type | TokenType::Const | TokenType::Struct if depth == 0 => return,
+                TokenType::LBrace => depth += 1,
+                TokenType::RBrace => {
+                    depth -= 1;
+
+                    if depth == 0 {
+                        self.bump();
+                        return;
+                    }
+                }
+                _ => {}
+            }
+
+            self.bump();
+        }
+    }
+
+    /// A missing `;`, `)` or `]` comes with a fix inserting it after the
+    /// previous token. Only a `;` missing at the end of a line is certain
+    /// enough to apply unchecked, and nothing is suggested for tokens that
+    /// came from a macro, whose text is elsewhere.
+    fn error_expected<T>(&self, expected: &str) -> Result<T> {
+        let mut err = ParseError {
+            lexeme: Box::new(self.peek().clone()),
+            code: "Q0501",
+            message: format!("Expected '{}'", expected),
+            fix: None,
+        };
+
+        let prev = &self.lexemes[self.pos.saturating_sub(1)];
+
+        if matches!(expected, ";" | ")" | "]")
+            && self.pos > 0
+            && prev.expansion.is_none()
+            && prev.span.infile_name == err.lexeme.span.infile_name
+        {
+            let applicable = expected == ";"
+                && (err.lexeme.token == TokenType::End
+                    || err.lexeme.span.lineno > prev.span.end_lineno);
+
+            err.fix = Some(Box::new(Fix {
+                span: prev.span.after(),
+                replacement: expected.to_string(),
+                applicable,
+            }));
+        }
+
+        Err(err)
+    }
+
+    fn expect(&mut self, token: TokenType, expected: &str) -> Result<Lexeme> {
+        if self.peek().token != token {
+            return self.error_expected(expected);
+        }
+
+        Ok(self.bump())
+    }
+
+    fn at_type(&self) -> bool {
+        matches!(
+            self.peek().token,
+            TokenType::Type | TokenType::Const | TokenType::Struct
+        )
+    }
+
+    fn at_struct_definition(&self) -> bool {
+        self.peek().token == TokenType::Struct
+            && self.peek_at(1).token == TokenType::Ident
+            && self.peek_at(2).token == TokenType::LBrace
+    }
+
+    /// `[const] TYPE` or `[const] struct NAME`
+    fn type_specifier(&mut self) -> Result<(Type, bool)> {
+        let is_const = self.eat(TokenType::Const);
+
+        if self.eat(TokenType::Struct) {
+            let name = self.expect(TokenType::Ident, "identifier")?;
+            let id = self.lookup_struct(&name.lex);
+            return Ok((Type::Struct(name.lex, id), is_const));
+        }
+
+        let l = self.expect(TokenType::Type, "type")?;
+
+        let ty = match l.lex.as_str() {
+            "void" => Type::Void,
+            "char" => Type::Char,
+            "int" => Type::Int,
+            _ => Type::Float,
+        };
+
+        Ok((ty, is_const))
+    }
+
+    /// The definition `struct NAME` refers to at this point.
+    fn lookup_struct(&self, name: &str) -> Option<usize> {
+        self.struct_scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(name).copied())
+    }
+
+    fn declaration(&mut self) -> Result<Vec<Decl>> {
+        if self.at_struct_definition() {
+            let def = self.struct_definition()?;
+            let ty = Type::Struct(def.name.clone(), Some(def.id));
+            let mut decls = vec![Decl::Struct(def)];
+
+            if !self.eat(TokenType::Semi) {
+                let name = self.expect(TokenType::Ident, "identifier")?;
+                decls.push(Decl::Var(self.variable_declaration(ty, false, name)?));
+            }
+
+            return Ok(decls);
+        }
+
+        if !self.at_type() {
+            return self.error_expected("function or global declaration");
+        }
+
+        let (ty, is_const) = self.type_specifier()?;
+        let name = self.expect(TokenType::Ident, "identifier")?;
+
+        if self.peek().token == TokenType::LPar && !is_const {
+            return Ok(vec![Decl::Function(self.function_definition(ty, name)?)]);
+        }
+
+        Ok(vec![Decl::Var(
+            self.variable_declaration(ty, is_const, name)?,
+        )])
+    }
+
+    /// `struct NAME { members }`; any declarators after the `}` are left for
+    /// the caller.
+    fn struct_definition(&mut self) -> Result<StructDef> {
+        self.expect(TokenType::Struct, "struct")?;
+        let name = self.expect(TokenType::Ident, "identifier")?;
+        self.expect(TokenType::LBrace, "{")?;
+
+        let mut members = Vec::new();
+
+        while !self.eat(TokenType::RBrace) {
+            if self.at_struct_definition() {
+                return self.error(
+                    "Q0502",
+                    "Struct definitions cannot be nested; define the struct first",
+                );
+            }
+
+            if !self.at_type() {
+                return self.error_expected("member declaration");
+            }
+
+            let (ty, is_const) = self.type_specifier()?;
+            let member = self.expect(TokenType::Ident, "identifier")?;
+            members.extend(self.variable_declaration(ty, is_const, member)?);
+        }
+
+        // registered only now, so a member cannot have the struct's own type
+        let id = self.struct_count;
+        self.struct_count += 1;
+        if let Some(scope) = self.struct_scopes.last_mut() {
+            scope.insert(name.lex.clone(), id);
+        }
+
+        Ok(StructDef {
+            id,
+            name: name.lex,
+            members,
+            span: name.span,
+        })
+    }
+
+    /// The declarators following `TYPE IDENT`, up to and including the `;`.
+    /// An array declared with `[]` takes its length from its initializer.
+    fn variable_declaration(
+        &mut self,
+        ty: Type,
+        is_const: bool,
+        name: Lexeme,
+    ) -> Result<Vec<VarDecl>> {
+        let mut vars = Vec::new();
+        let mut name = name;
+
+        loop {
+            let mut var_ty = self.array_dimensions(ty.clone())?;
+
+            let init = if self.eat(TokenType::Assign) {
+                Some(self.initializer()?)
+            } else {
+                None
+            };
+
+            if let Type::Array(_, size @ None) = &mut var_ty {
+                *size = match &init {
+                    Some(Initializer::List(items, _)) => Some(items.len()),
+                    Some(Initializer::Expr(Expr {
+                        kind: ExprKind::StrLit(s),
+                        ..
+                    })) => Some(s.len() + 1),
+                    _ => None,
+                };
+            }
+
+            vars.push(VarDecl {
+                ty: var_ty,
+                name: name.lex,
+                is_const,
+                init,
+                span: name.span,
+            });
+
+            if self.eat(TokenType::Semi) {
+                return Ok(vars);
+            }
+
+            if !self.eat(TokenType::Comma) {
+                return self.error_expected(";");
+            }
+
+            name = self.expect(TokenType::Ident, "identifier")?;
+        }
+    }
+
+    /// Any number of `[size]` suffixes. Only the first may be left empty, as
+    /// in `int m[][10]`, for a parameter or an array sized by its initializer.
+    fn array_dimensions(&mut self, ty: Type) -> Result<Type> {
+        let mut dims = Vec::new();
+
+        while self.eat(TokenType::LBrak) {
+            if self.peek().token == TokenType::RBrak {
+                if !dims.is_empty() {
+                    return self.error("Q0503", "Only the first array dimension may be omitted");
+                }
+
+                dims.push(None);
+            } else {
+                dims.push(Some(self.array_size()?));
+            }
+
+            self.expect(TokenType::RBrak, "]")?;
+        }
+
+        Ok(dims
+            .into_iter()
+            .rev()
+            .fold(ty, |ty, len| Type::Array(Box::new(ty), len)))
+    }
+
+    fn array_size(&mut self) -> Result<usize> {
+        let start = self.peek().clone();
+        let e = self.ternary()?;
+
+        let error = |message: &str| ParseError {
+            lexeme: Box::new(start.clone()),
+            code: "Q0504",
+            message: message.to_string(),
+            fix: None,
+        };
+
+        match const_value(&e) {
+            None => Err(error("Array size must be a constant integer expression")),
+            Some(n) if n <= 0 => Err(error("Array size must be positive")),
+            Some(n) => Ok(n as usize),
+        }
+    }
+
+    /// An expression, or `{ initializer, ... }` with an optional trailing
+    /// comma.
+    fn initializer(&mut self) -> Result<Initializer> {
+        if self.peek().token != TokenType::LBrace {
+            return Ok(Initializer::Expr(self.expression()?));
+        }
+
+        let open = self.bump();
+        let mut items = Vec::new();
+
+        while self.peek().token != TokenType::RBrace {
+            match self.initializer() {
+                Ok(item) => items.push(item),
+                Err(err) => {
+                    self.skip_initializer_list();
+                    return Err(err);
+                }
+            }
+
+            if !self.eat(TokenType::Comma) {
+                break;
+            }
+        }
+
+        if let Err(err) = self.expect(TokenType::RBrace, "}") {
+            self.skip_initializer_list();
+            return Err(err);
+        }
+
+        Ok(Initializer::List(items, open.span.to(self.prev_span())))
+    }
+
+    /// Skips past the `}` closing the current initializer list, so that
+    /// recovery does not mistake it for the end of a block. Stops early at a
+    /// `;`, which cannot appear inside one.
+    fn skip_initializer_list(&mut self) {
+        let mut depth = 1;
+
+        loop {
+            match self.peek().token {
+                TokenType::End | TokenType::Semi => return,
+                TokenType::LBrace => depth += 1,
+                TokenType::RBrace => {
+                    depth -= 1;
+
+                    if depth == 0 {
+                        self.bump();
+                        return;
+                    }
+                }
+                _ => {}
+            }
+
+            self.bump();
+        }
+    }
+
+    /// A function definition, or a prototype when the parameter list is
+    /// followed by `;`.
+    fn function_definition(&mut self, return_type: Type, name: Lexeme) -> Result<FunctionDef> {
+        self.expect(TokenType::LPar, "(")?;
+        let params = self.formal_parameters()?;
+        self.expect(TokenType::RPar, ")")?;
+
+        let body = if self.eat(TokenType::Semi) {
+            None
+        } else if self.peek().token == TokenType::LBrace {
+            Some(self.block()?)
+        } else {
+            return self.error_expected("{");
+        };
+
+        Ok(FunctionDef {
+            return_type,
+            name: name.lex,
+            params,
+            body,
+            span: name.span,
+        })
+    }
+
+    fn formal_parameters(&mut self) -> Result<Vec<VarDecl>> {
+        let mut params = Vec::new();
+
+        // `f(void)` takes no parameters
+        if self.peek().lex == "void" && self.peek_at(1).token == TokenType::RPar {
+            self.bump();
+            return Ok(params);
+        }
+
+        if self.peek().token == TokenType::RPar {
+            return Ok(params);
+        }
+
+        loop {
+            if !self.at_type() {
+                return self.error_expected("type");
+            }
+
+            let start = self.peek().span.clone();
+            let (mut ty, is_const) = self.type_specifier()?;
+
+            // parameter names may be left out, as in `int f(int, char[]);`
+            let (name, span) = if self.peek().token == TokenType::Ident {
+                let name = self.bump();
+                (name.lex, name.span)
+            } else {
+                (String::new(), start.to(self.prev_span()))
+            };
+
+            ty = self.array_dimensions(ty)?;
+
+            // arrays are passed by reference, so the leading length is ignored
+            if let Type::Array(_, len) = &mut ty {
+                *len = None;
+            }
+
+            params.push(VarDecl {
+                ty,
+                name,
+                is_const,
+                init: None,
+                span,
+            });
+
+            if !self.eat(TokenType::Comma) {
+                return Ok(params);
+            }
+        }
+    }
+
+    /// `{ statements }`, where local declarations may appear anywhere.
+    fn block(&mut self) -> Result<Block> {
+        self.struct_scopes.push(HashMap::new());
+        let block = self.block_body();
+        self.struct_scopes.pop();
+        block
+    }
+
+    fn block_body(&mut self) -> Result<Block> {
+        let open = self.expect(TokenType::LBrace, "{")?;
+        let mut stmts = Vec::new();
+
+        while self.peek().token != TokenType::RBrace {
+            if self.peek().token == TokenType::End {
+                return self.error_expected("}");
+            }
+
+            let parsed = if self.at_type() {
+                self.local_declaration()
+            } else {
+                self.statement().map(|stmt| vec![stmt])
+            };
+
+            match parsed {
+                Ok(parsed) => stmts.extend(parsed),
+                Err(err) => self.recover(err)?,
+            }
+        }
+
+        self.bump();
+
+        Ok(Block {
+            stmts,
+            span: open.span.to(self.prev_span()),
+        })
+    }
+
+    fn local_declaration(&mut self) -> Result<Vec<Stmt>> {
+        if self.at_struct_definition() {
+            let def = self.struct_definition()?;
+            let ty = Type::Struct(def.name.clone(), Some(def.id));
+            let mut stmts = vec![Stmt::Struct(def)];
+
+            if !self.eat(TokenType::Semi) {
+                let name = self.expect(TokenType::Ident, "identifier")?;
+                stmts.push(Stmt::Var(self.variable_declaration(ty, false, name)?));
+            }
+
+            return Ok(stmts);
+        }
+
+        let (ty, is_const) = self.type_specifier()?;
+        let name = self.expect(TokenType::Ident, "identifier")?;
+
+        Ok(vec![Stmt::Var(
+            self.variable_declaration(ty, is_const, name)?,
+        )])
+    }
+
+    fn statement(&mut self) -> Result<Stmt> {
+        let start = self.peek().span.clone();
+
+        let stmt = match self.peek().token {
+            TokenType::Semi => {
+                self.bump();
+                Stmt::Empty(start)
+            }
+            TokenType::LBrace => Stmt::Block(self.block()?),
+            TokenType::Break => {
+                self.bump();
+                self.expect(TokenType::Semi, ";")?;
+                Stmt::Break(start)
+            }
+            TokenType::Continue => {
+                self.bump();
+                self.expect(TokenType::Semi, ";")?;
+                Stmt::Continue(start)
+            }
+            TokenType::Return => {
+                self.bump();
+
+                let value = if self.peek().token == TokenType::Semi {
+                    None
+                } else {
+                    Some(self.expression()?)
+                };
+
+                self.expect(TokenType::Semi, ";")?;
+
+                Stmt::Return(Return {
+                    value,
+                    span: start.to(self.prev_span()),
+                })
+            }
+            TokenType::If => self.if_p()?,
+            TokenType::For => self.for_p()?,
+            TokenType::While => self.while_p()?,
+            TokenType::Do => self.do_p()?,
+            _ => {
+                let e = self.expression()?;
+                self.expect(TokenType::Semi, ";")?;
+                Stmt::Expr(e)
+            }
+        };
+
+        Ok(stmt)
+    }
+
+    fn condition(&mut self) -> Result<Expr> {
+        self.expect(TokenType::LPar, "(")?;
+        let e = self.expression()?;
+        self.expect(TokenType::RPar, ")")?;
+
+        Ok(e)
+    }
+
+    fn if_p(&mut self) -> Result<Stmt> {
+        let start = self.bump().span;
+        let cond = self.condition()?;
+        let then = Box::new(self.statement()?);
+
+        let els = if self.eat(TokenType::Else) {
+            Some(Box::new(self.statement()?))
+        } else {
+            None
+        };
+
+        Ok(Stmt::If(If {
+            cond,
+            then,
+            els,
+            span: start.to(self.prev_span()),
+        }))
+    }
+
+    fn for_p(&mut self) -> Result<Stmt> {
+        let start = self.bump().span;
+        self.expect(TokenType::LPar, "(")?;
+
+        let init = if self.at_type() {
+            let (ty, is_const) = self.type_specifier()?;
+            let name = self.expect(TokenType::Ident, "identifier")?;
+            let vars = self.variable_declaration(ty, is_const, name)?;
+            Some(Box::new(Stmt::Var(vars)))
+        } else {
+            self.optional_expression(TokenType::Semi, ";")?
+                .map(|e| Box::new(Stmt::Expr(e)))
+        };
+
+        let cond = self.optional_expression(TokenType::Semi, ";")?;
+        let step = self.optional_expression(TokenType::RPar, ")")?;
+        let body = Box::new(self.statement()?);
+
+        Ok(Stmt::For(Box::new(For {
+            init,
+            cond,
+            step,
+            body,
+            span: start.to(self.prev_span()),
+        })))
+    }
+
+    /// An expression that may be left out, followed by `end`.
+    fn optional_expression(&mut self, end: TokenType, expected: &str) -> Result<Option<Expr>> {
+        let e = if self.peek().token == end {
+            None
+        } else {
+            Some(self.expression()?)
+        };
+
+        self.expect(end, expected)?;
+
+        Ok(e)
+    }
+
+    fn while_p(&mut self) -> Result<Stmt> {
+        let start = self.bump().span;
+        let cond = self.condition()?;
+        let body = Box::new(self.statement()?);
+
+        Ok(Stmt::While(While {
+            cond,
+            body,
+            span: start.to(self.prev_span()),
+        }))
+    }
+
+    fn do_p(&mut self) -> Result<Stmt> {
+        let start = self.bump().span;
+        let body = Box::new(self.statement()?);
+        self.expect(TokenType::While, "while")?;
+        let cond = self.condition()?;
+        self.expect(TokenType::Semi, ";")?;
+
+        Ok(Stmt::DoWhile(DoWhile {
+            body,
+            cond,
+            span: start.to(self.prev_span()),
+        }))
+    }
+
+    fn expression(&mut self) -> Result<Expr> {
+        self.assignment()
+    }
+
+    /// Assignment is right associative and binds loosest; whether the left
+    /// side is assignable is up to the checker.
+    fn assignment(&mut self) -> Result<Expr> {
+        let lhs = self.ternary()?;
+
+        let op = match self.peek().token {
+            TokenType::Assign => None,
+            TokenType::PlusAssign => Some(BinaryOp::Add),
+            TokenType::MinusAssign => Some(BinaryOp::Sub),
+            TokenType::StarAssign => Some(BinaryOp::Mul),
+            TokenType::SlashAssign => Some(BinaryOp::Div),
+            TokenType::ModAssign => Some(BinaryOp::Mod),
+            TokenType::PipeAssign => Some(BinaryOp::BitOr),
+            TokenType::AmpAssign => Some(BinaryOp::BitAnd),
+            TokenType::CaretAssign => Some(BinaryOp::BitXor),
+            TokenType::LShiftAssign => Some(BinaryOp::Shl),
+            TokenType::RShiftAssign => Some(BinaryOp::Shr),
+            _ => return Ok(lhs),
+        };
+
+        let expansion = self.bump().expansion;
+        let rhs = self.assignment()?;
+
+        Ok(Expr {
+            span: lhs.span.to(&rhs.span),
+            kind: ExprKind::Assign {
+                op,
+                lhs: Box::new(lhs),
+                rhs: Box::new(rhs),
+            },
+            ty: OnceCell::new(),
+            expansion,
+        })
+    }
+
+    fn ternary(&mut self) -> Result<Expr> {
+        let cond = self.dpipe()?;
+
+        if self.peek().token != TokenType::Quest {
+            return Ok(cond);
+        }
+
+        let expansion = self.bump().expansion;
+
+        let then = self.expression()?;
+        self.expect(TokenType::Colon, ":")?;
+        let els = self.ternary()?;
+
+        Ok(Expr {
+            span: cond.span.to(&els.span),
+            kind: ExprKind::Ternary {
+                cond: Box::new(cond),
+                then: Box::new(then),
+                els: Box::new(els),
+            },
+            ty: OnceCell::new(),
+            expansion,
+        })
+    }
+
+    /// One left-associative precedence level: `operand (op operand)*`.
+    fn binary(
+        &mut self,
+        ops: &[(TokenType, BinaryOp)],
+        operand: fn(&mut Self) -> Result<Expr>,
+    ) -> Result<Expr> {
+        let mut lhs = operand(self)?;
+
+        while let Some(&(_, op)) = ops.iter().find(|(t, _)| *t == self.peek().token) {
+            let expansion = self.bump().expansion;
+            let rhs = operand(self)?;
+
+            lhs = Expr {
+                span: lhs.span.to(&rhs.span),
+                kind: ExprKind::Binary {
+                    op,
+                    lhs: Box::new(lhs),
+                    rhs: Box::new(rhs),
+                },
+                ty: OnceCell::new(),
+                expansion,
+            };
+        }
+
+        Ok(lhs)
+    }
+
+    fn dpipe(&mut self) -> Result<Expr> {
+        self.binary(&[(TokenType::DPipe, BinaryOp::LogOr)], Self::damp)
+    }
+
+    fn damp(&mut self) -> Result<Expr> {
+        self.binary(&[(TokenType::DAmp, BinaryOp::LogAnd)], Self::pipe)
+    }
+
+    fn pipe(&mut self) -> Result<Expr> {
+        self.binary(&[(TokenType::Pipe, BinaryOp::BitOr)], Self::caret)
+    }
+
+    fn caret(&mut self) -> Result<Expr> {
+        self.binary(&[(TokenType::Caret, BinaryOp::BitXor)], Self::amp)
+    }
+
+    fn amp(&mut self) -> Result<Expr> {
+        self.binary(&[(TokenType::Amp, BinaryOp::BitAnd)], Self::eq)
+    }
+
+    fn eq(&mut self) -> Result<Expr> {
+        self.binary(
+            &[(TokenType::Eq, BinaryOp::Eq), (TokenType::Ne, BinaryOp::Ne)],
+            Self::comp,
+        )
+    }
+
+    fn comp(&mut self) -> Result<Expr> {
+        self.binary(
+            &[
+                (TokenType::Lt, BinaryOp::Lt),
+                (TokenType::Le, BinaryOp::Le),
+                (TokenType::Gt, BinaryOp::Gt),
+                (TokenType::Ge, BinaryOp::Ge),
+            ],
+            Self::shift,
+        )
+    }
+
+    fn shift(&mut self) -> Result<Expr> {
+        self.binary(
+            &[
+                (TokenType::LShift, BinaryOp::Shl),
+                (TokenType::RShift, BinaryOp::Shr),
+            ],
+            Self::sum,
+        )
+    }
+
+    fn sum(&mut self) -> Result<Expr> {
+        self.binary(
+            &[
+                (TokenType::Plus, BinaryOp::Add),
+                (TokenType::Minus, BinaryOp::Sub),
+            ],
+            Self::product,
+        )
+    }
+
+    fn product(&mut self) -> Result<Expr> {
+        self.binary(
+            &[
+                (TokenType::Star, BinaryOp::Mul),
+                (TokenType::Slash, BinaryOp::Div),
+                (TokenType::Mod, BinaryOp::Mod),
+            ],
+            Self::unary,
+        )
+    }
+
+    /// Prefix operators and casts.
+    fn unary(&mut self) -> Result<Expr> {
+        let start = self.peek().span.clone();
+        let expansion = self.peek().expansion.clone();
+
+        let op = match self.peek().token {
+            TokenType::Minus => UnaryOp::Neg,
+            TokenType::Plus => UnaryOp::Plus,
+            TokenType::Bang => UnaryOp::Not,
+            TokenType::Tilde => UnaryOp::BitNot,
+            TokenType::Amp => UnaryOp::AddrOf,
+            TokenType::Star => UnaryOp::Deref,
+            TokenType::Incr | TokenType::Decr => {
+                let op = if self.bump().token == TokenType::Incr {
+                    IncDecOp::PreIncr
+                } else {
+                    IncDecOp::PreDecr
+                };
+                let operand = self.unary()?;
+
+                return Ok(Expr {
+                    span: start.to(&operand.span),
+                    kind: ExprKind::IncDec {
+                        op,
+                        operand: Box::new(operand),
+                    },
+                    ty: OnceCell::new(),
+                    expansion,
+                });
+            }
+            TokenType::LPar
+                if matches!(
+                    self.peek_at(1).token,
+                    TokenType::Type | TokenType::Const | TokenType::Struct
+                ) =>
+            {
+                self.bump();
+                let (ty, _) = self.type_specifier()?;
+                self.expect(TokenType::RPar, ")")?;
+                let expr = self.unary()?;
+
+                return Ok(Expr {
+                    span: start.to(&expr.span),
+                    kind: ExprKind::Cast {
+                        ty,
+                        expr: Box::new(expr),
+                    },
+                    ty: OnceCell::new(),
+                    expansion,
+                });
+            }
+            _ => return self.postfix(),
+        };
+
+        self.bump();
+        let operand = self.unary()?;
+
+        Ok(Expr {
+            span: start.to(&operand.span),
+            kind: ExprKind::Unary {
+                op,
+                operand: Box::new(operand),
+            },
+            ty: OnceCell::new(),
+            expansion,
+        })
+    }
+
+    /// Indexing, member access and postfix `++`/`--`.
+    fn postfix(&mut self) -> Result<Expr> {
+        let mut e = self.primary()?;
+
+        loop {
+            let start = e.span.clone();
+            let expansion = self.peek().expansion.clone();
+
+            let kind = match self.peek().token {
+                TokenType::LBrak => {
+                    self.bump();
+                    let index = self.expression()?;
+                    self.expect(TokenType::RBrak, "]")?;
+
+                    ExprKind::Index {
+                        array: Box::new(e),
+                        index: Box::new(index),
+                    }
+                }
+                TokenType::Dot => {
+                    self.bump();
+                    let member = self.expect(TokenType::Ident, "identifier")?;
+
+                    ExprKind::Member {
+                        base: Box::new(e),
+                        member: member.lex,
+                        member_span: member.span,
+                    }
+                }
+                TokenType::Incr | TokenType::Decr => {
+                    let op = if self.bump().token == TokenType::Incr {
+                        IncDecOp::PostIncr
+                    } else {
+                        IncDecOp::PostDecr
+                    };
+
+                    ExprKind::IncDec {
+                        op,
+                        operand: Box::new(e),
+                    }
+                }
+                _ => return Ok(e),
+            };
+
+            e = Expr {
+                kind,
+                span: start.to(self.prev_span()),
+                ty: OnceCell::new(),
+                expansion,
+            };
+        }
+    }
+
+    fn primary(&mut self) -> Result<Expr> {
+        let l = self.peek().clone();
+
+        let kind = match l.token {
+            TokenType::IntLit => ExprKind::IntLit(int_value(&l)?),
+            TokenType::RealLit => match l.lex.parse() {
+                Ok(v) => ExprKind::RealLit(v),
+                Err(_) => return self.error("Q0004", "Malformed real literal"),
+            },
+            TokenType::CharLit => {
+                let bytes = unescape(&l)?;
+
+                if bytes.len() != 1 {
+                    return self
+                        .error("Q0006", "Character literal must hold exactly one character");
+                }
+
+                ExprKind::CharLit(bytes[0])
+            }
+            TokenType::StrLit => {
+                // adjacent string literals are joined
+                let mut bytes = Vec::new();
+
+                while self.peek().token == TokenType::StrLit {
+                    bytes.extend(unescape(self.peek())?);
+                    self.bump();
+                }
+
+                return Ok(Expr {
+                    kind: ExprKind::StrLit(bytes),
+                    span: l.span.to(self.prev_span()),
+                    ty: OnceCell::new(),
+                    expansion: l.expansion,
+                });
+            }
+            TokenType::Ident if self.peek_at(1).token == TokenType::LPar => {
+                self.bump();
+                self.bump();
+
+                let mut args = Vec::new();
+
+                if self.peek().token != TokenType::RPar {
+                    loop {
+                        args.push(self.expression()?);
+
+                        if !self.eat(TokenType::Comma) {
+                            break;
+                        }
+                    }
+                }
+
+                self.expect(TokenType::RPar, ")")?;
+
+                return Ok(Expr {
+                    kind: ExprKind::Call {
+                        name: l.lex,
+                        name_span: l.span.clone(),
+                        args,
+                    },
+                    span: l.span.to(self.prev_span()),
+                    ty: OnceCell::new(),
+                    expansion: l.expansion,
+                });
+            }
+            TokenType::Ident => ExprKind::Ident(l.lex.clone()),
+            TokenType::LPar => {
+                self.bump();
+                let mut e = self.expression()?;
+                self.expect(TokenType::RPar, ")")?;
+
+                e.span = l.span.to(self.prev_span());
+                return Ok(e);
+            }
+            _ => return self.error_expected("identifier (within expression)"),
+        };
+
+        self.bump();
+
+        Ok(Expr {
+            kind,
+            span: l.span,
+            ty: OnceCell::new(),
+            expansion: l.expansion,
+        })
+    }
+}
+
+/// Value of an integer constant expression, or `None` if `e` is not one.
+pub fn const_value(e: &Expr) -> Option<i64> {
+    let v = match &e.kind {
+        ExprKind::IntLit(v) => *v,
+        ExprKind::CharLit(c) => *c as i64,
+        ExprKind::Unary { op, operand } => {
+            let v = const_value(operand)?;
+
+            match op {
+                UnaryOp::Neg => v.checked_neg()?,
+                UnaryOp::Plus => v,
+                UnaryOp::Not => (v == 0) as i64,
+                UnaryOp::BitNot => !v,
+                UnaryOp::AddrOf | UnaryOp::Deref => return None,
+            }
+        }
+        ExprKind::Binary { op, lhs, rhs } => {
+            let l = const_value(lhs)?;
+            let r = const_value(rhs)?;
+
+            match op {
+                BinaryOp::LogOr => (l != 0 || r != 0) as i64,
+                BinaryOp::LogAnd => (l != 0 && r != 0) as i64,
+                BinaryOp::BitOr => l | r,
+                BinaryOp::BitXor => l ^ r,
+                BinaryOp::BitAnd => l & r,
+                BinaryOp::Eq => (l == r) as i64,
+                BinaryOp::Ne => (l != r) as i64,
+                BinaryOp::Lt => (l < r) as i64,
+                BinaryOp::Le => (l <= r) as i64,
+                BinaryOp::Gt => (l > r) as i64,
+                BinaryOp::Ge => (l >= r) as i64,
+                BinaryOp::Shl => l.checked_shl(r.try_into().ok()?)?,
+                BinaryOp::Shr => l.checked_shr(r.try_into().ok()?)?,
+                BinaryOp::Add => l.checked_add(r)?,
+                BinaryOp::Sub => l.checked_sub(r)?,
+                BinaryOp::Mul => l.checked_mul(r)?,
+                BinaryOp::Div => l.checked_div(r)?,
+                BinaryOp::Mod => l.checked_rem(r)?,
+            }
+        }
+        ExprKind::Ternary { cond, then, els } => {
+            if const_value(cond)? != 0 {
+                const_value(then)?
+            } else {
+                const_value(els)?
+            }
+        }
+        ExprKind::Cast { ty, expr } if ty.is_integer() => const_value(expr)?,
+        _ => return None,
+    };
+
+    Some(v)
+}
+
+/// Whether `e` can be evaluated before the program runs, as a global's
+/// initializer must be: literals combined by operators other than
+/// assignment, increment and decrement.
+pub fn is_constant(e: &Expr) -> bool {
+    match &e.kind {
+        ExprKind::IntLit(_) | ExprKind::RealLit(_) | ExprKind::CharLit(_) | ExprKind::StrLit(_) => {
+            true
+        }
+        ExprKind::Unary { op, operand } => {
+            !matches!(op, UnaryOp::AddrOf | UnaryOp::Deref) && is_constant(operand)
+        }
+        ExprKind::Binary { lhs, rhs, .. } => is_constant(lhs) && is_constant(rhs),
+        ExprKind::Ternary { cond, then, els } => {
+            is_constant(cond) && is_constant(then) && is_constant(els)
+        }
+        ExprKind::Cast { expr, .. } => is_constant(expr),
+        _ => false,
+    }
+}
+
+fn int_value(l: &Lexeme) -> Result<i64> {
+    let text = l.lex.as_str();
+
+    let value = if let Some(hex) = text.strip_prefix("0x").or(text.strip_prefix("0X")) {
+        i64::from_str_radix(hex, 16)
+    } else if text.len() > 1 && text.starts_with('0') {
+        i64::from_str_radix(&text[1..], 8)
+    } else {
+        text.parse()
+    };
+
+    value.map_err(|_| ParseError {
+        lexeme: Box::new(l.clone()),
+        code: "Q0004",
+        message: "Malformed integer literal".to_string(),
+        fix: None,
+    })
+}
+
+/// Resolves the escape sequences inside a character or string literal.
+fn unescape(l: &Lexeme) -> Result<Vec<u8>> {
+    let text = l.lex.as_bytes();
+    let text = &text[1..text.len() - 1];
+    let mut bytes = Vec::with_capacity(text.len());
+    let mut i = 0;
+
+    let error = |message: &str| ParseError {
+        lexeme: Box::new(l.clone()),
+        code: "Q0007",
+        message: message.to_string(),
+        fix: None,
+    };
+
+    while i < text.len() {
+        if text[i] != b'\\' {
+            bytes.push(text[i]);
+            i += 1;
+            continue;
+        }
+
+        let c = *text
+            .get(i + 1)
+            .ok_or_else(|| error("Malformed escape sequence"))?;
+        i += 2;
+
+        let b = match c {
+            b'n' => b'\n',
+            b't' => b'\t',
+            b'r' => b'\r',
+            b'a' => 0x07,
+            b'b' => 0x08,
+            b'f' => 0x0c,
+            b'v' => 0x0b,
+            b'\\' | b'\'' | b'"' | b'?' => c,
+            b'x' => {
+                let digits = text[i..]
+                    .iter()
+                    .take_while(|c| c.is_ascii_hexdigit())
+                    .count();
+
+                if digits == 0 {
+                    return Err(error("Malformed escape sequence"));
+                }
+
+                let s = std::str::from_utf8(&text[i..i + digits]).unwrap();
+                i += digits;
+
+                u32::from_str_radix(s, 16)
+                    .ok()
+                    .and_then(|v| u8::try_from(v).ok())
+                    .ok_or_else(|| error("Escape sequence out of range"))?
+            }
+            b'0'..=b'7' => {
+                let digits = 1 + text[i..]
+                    .iter()
+                    .take(2)
+                    .take_while(|c| (b'0'..=b'7').contains(*c))
+                    .count();
+                let s = std::str::from_utf8(&text[i - 1..i - 1 + digits]).unwrap();
+                i += digits - 1;
+
+                u8::try_from(u32::from_str_radix(s, 8).unwrap())
+                    .map_err(|_| error("Escape sequence out of range"))?
+            }
+            _ => return Err(error("Unknown escape sequence")),
+        };
+
+        bytes.push(b);
+    }
+
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::Lexer;
+
+    fn parse(src: &str) -> (Program, Vec<ParseError>) {
+        let lexemes = Lexer::new("test.c", src).lex().expect("input lexes");
+        Parser::new(lexemes).parse()
+    }
+
+    fn parse_ok(src: &str) -> Program {
+        let (program, errors) = parse(src);
+        assert!(errors.is_empty(), "unexpected errors: {:?}", errors);
+        program
+    }
+
+    /// The expression statements in the body of `int f() { <body> }`.
+    fn statements(body: &str) -> Vec<Expr> {
+        let program = parse_ok(&format!("int f() {{ {} }}", body));
+
+        let Decl::Function(f) = &program.decls[0] else {
+            panic!("expected a function");
+        };
+
+        let body = f.body.as_ref().expect("a definition");
+
+        body.stmts
+            .iter()
+            .map(|s| match s {
+                Stmt::Expr(e) => e.clone(),
+                s => panic!("expected an expression statement, found {:?}", s),
+            })
+            .collect()
+    }
+
+    /// `e` with every operation in parentheses.
+    fn grouped(e: &Expr) -> String {
+        match &e.kind {
+            ExprKind::IntLit(v) => v.to_string(),
+            ExprKind::Ident(name) => name.clone(),
+            ExprKind::Unary { op, operand } => format!("({}{})", op, grouped(operand)),
+            ExprKind::Binary { op, lhs, rhs } => {
+                format!("({} {} {})", grouped(lhs), op, grouped(rhs))
+            }
+            ExprKind::Assign { lhs, rhs, .. } => format!("({} = {})", grouped(lhs), grouped(rhs)),
+            ExprKind::Ternary { cond, then, els } => {
+                format!("({} ? {} : {})", grouped(cond), grouped(then), grouped(els))
+            }
+            kind => panic!("unexpected {:?}", kind),
+        }
+    }
+
+    #[test]
+    fn precedence() {
+        let exprs = statements("a = b = 1 + 2 * 3 - -4; x || y && z == 1; c ? 1 : d ? 2 : 3;");
+        let exprs: Vec<String> = exprs.iter().map(grouped).collect();
+
+        assert_eq!(
+            exprs,
+            [
+                "(a = (b = ((1 + (2 * 3)) - (-4))))",
+                "(x || (y && (z == 1)))",
+                "(c ? 1 : (d ? 2 : 3))",
+            ]
+        );
+    }
+
+    #[test]
+    fn prototypes_have_no_body() {
+        let program = parse_ok("int f(int a, float);\nint f(int a, float b) { return a; }");
+        let bodies: Vec<bool> = program
+            .decls
+            .iter()
+            .map(|d| matches!(d, Decl::Function(f) if f.body.is_some()))
+            .collect();
+
+        assert_eq!(bodies, [false, true]);
+    }
+
+    #[test]
+    fn shifts_xor_and_compound_assignment() {
+        let exprs = statements("a ^ b | c << 1 + 2 & d; x <<= 2;");
+
+        assert_eq!(grouped(&exprs[0]), "((a ^ b) | ((c << (1 + 2)) & d))");
+        assert!(matches!(
+            exprs[1].kind,
+            ExprKind::Assign {
+                op: Some(BinaryOp::Shl),
+                ..
+            }
+        ));
+    }
+
+    /// The global variables `src` declares.
+    fn globals(src: &str) -> Vec<VarDecl> {
+        let program = parse_ok(src);
+        let mut vars = Vec::new();
+
+        for decl in program.decls {
+            if let Decl::Var(v) = decl {
+                vars.extend(v);
+            }
+        }
+
+        vars
+    }
+
+    fn init_expr(v: &VarDecl) -> &Expr {
+        match &v.init {
+            Some(Initializer::Expr(e)) => e,
+            init => panic!("expected an expression initializer, found {:?}", init),
+        }
+    }
+
+    #[test]
+    fn array_length_from_initializer() {
+        let vars = globals("int a[] = {1, 2, 3}; char s[] = \"hi\";");
+
+        assert_eq!(vars[0].ty, Type::Array(Box::new(Type::Int), Some(3)));
+        assert_eq!(vars[1].ty, Type::Array(Box::new(Type::Char), Some(3)));
+    }
+
+    #[test]
+    fn array_dimensions() {
+        let vars = globals("int m[2][1 + 2]; char s[][4] = {\"ab\", \"cd\"};");
+        let types: Vec<String> = vars.iter().map(|v| v.ty.to_string()).collect();
+
+        assert_eq!(types, ["int[2][3]", "char[2][4]"]);
+
+        for src in ["int a[0];", "int a[n];", "int a[2][];"] {
+            let (_, errors) = parse(src);
+            assert_eq!(errors.len(), 1, "{}", src);
+        }
+    }
+
+    #[test]
+    fn constant_values() {
+        let vars = globals(
+            "int a = 1 + 2 * 3 - 8 / 2; int b = 1 << 2 + 1; int c = 2 > 1 ? 7 : 9; int d = a;",
+        );
+        let values: Vec<Option<i64>> = vars.iter().map(|v| const_value(init_expr(v))).collect();
+
+        assert_eq!(values, [Some(3), Some(8), Some(7), None]);
+    }
+
+    #[test]
+    fn constants() {
+        let vars = globals("int a = -(1 + 2) * 3; float b = (float)1 / 2; int c = a; int d = f();");
+        let constant: Vec<bool> = vars.iter().map(|v| is_constant(init_expr(v))).collect();
+
+        assert_eq!(constant, [true, true, false, false]);
+    }
+
+    #[test]
+    fn struct_types_refer_to_the_definition_in_scope() {
+        let program = parse_ok(
+            "struct P { int a; };\n\
+             int f() { struct P { int x; }; struct P q; return 0; }\n\
+             struct P g;\n\
+             struct Q h;",
+        );
+
+        let Decl::Function(f) = &program.decls[1] else {
+            panic!("expected a function");
+        };
+        let Some(Stmt::Var(q)) = f.body.as_ref().map(|b| &b.stmts[1]) else {
+            panic!("expected a declaration");
+        };
+        assert_eq!(q[0].ty, Type::Struct("P".to_string(), Some(1)));
+
+        let Decl::Var(g) = &program.decls[2] else {
+            panic!("expected a global");
+        };
+        assert_eq!(g[0].ty, Type::Struct("P".to_string(), Some(0)));
+
+        let Decl::Var(h) = &program.decls[3] else {
+            panic!("expected a global");
+        };
+        assert_eq!(h[0].ty, Type::Struct("Q".to_string(), None));
+    }
+
+    #[test]
+    fn missing_semicolon_fix() {
+        let (_, errors) = parse("int f() {\n    return 0\n}");
+        let fix = errors[0].fix.as_ref().expect("a fix is attached");
+
+        assert_eq!(fix.replacement, ";");
+        assert!(fix.applicable);
+        assert_eq!((fix.span.lineno, fix.span.col), (2, 13));
+        assert_eq!(fix.span.start, fix.span.end);
+    }
+
+    #[test]
+    fn escapes() {
+        let exprs = statements("'\\n'; '\\x41'; '\\101';");
+        let values: Vec<&ExprKind> = exprs.iter().map(|e| &e.kind).collect();
+
+        assert_eq!(
+            values,
+            [
+                &ExprKind::CharLit(10),
+                &ExprKind::CharLit(65),
+                &ExprKind::CharLit(65)
+            ]
+        );
+
+        for src in ["int f() { '\\400'; }", "int f() { '\\x100'; }"] {
+            let (_, errors) = parse(src);
+            assert_eq!(errors[0].code, "Q0007", "{}", src);
+        }
+    }
+
+    #[test]
+    fn recovers_after_errors() {
+        let (program, errors) = parse("int f() { int a; a = ; a = ; return 0 }\nint g;");
+        let at: Vec<&str> = errors.iter().map(|e| e.lexeme.lex.as_str()).collect();
+
+        assert_eq!(at, [";", ";", "}"]);
+        assert_eq!(program.decls.len(), 2);
+    }
+
+    #[test]
+    fn error_limit() {
+        let lexemes = Lexer::new("test.c", "int a = ; int b = ; int c = ;")
+            .lex()
+            .unwrap();
+        let (_, errors) = Parser::new(lexemes).error_limit(2).parse();
+
+        assert_eq!(errors.len(), 2);
+    }
+
+    #[test]
+    fn types_and_operators_print_as_c() {
+        let array = Type::Array(Box::new(Type::Char), Some(4));
+
+        assert_eq!(array.to_string(), "char[4]");
+        assert_eq!(Type::Float.to_string(), "float");
+        assert_eq!(UnaryOp::BitNot.to_string(), "~");
+        assert_eq!(IncDecOp::PostDecr.to_string(), "--");
+        assert_eq!(BinaryOp::Le.to_string(), "<=");
+    }
+}
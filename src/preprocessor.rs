@@ -0,0 +1,968 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use crate::diagnostic::Diagnostic;
+use crate::lexer::{Expansion, LexError, Lexeme, Lexer, Span, TokenType};
+use crate::warning::{Suppression, Warning};
+
+/// Includes nested deeper than this are assumed to be runaway recursion.
+pub const MAX_INCLUDE_DEPTH: usize = 32;
+
+#[derive(Debug)]
+pub enum PreprocessorError {
+    Lex(LexError),
+    Directive {
+        span: Span,
+        code: &'static str,
+        message: String,
+        expansion: Option<Rc<Expansion>>,
+    },
+}
+
+impl From<LexError> for PreprocessorError {
+    fn from(err: LexError) -> Self {
+        PreprocessorError::Lex(err)
+    }
+}
+
+impl From<PreprocessorError> for Diagnostic {
+    fn from(err: PreprocessorError) -> Self {
+        match err {
+            PreprocessorError::Lex(err) => err.into(),
+            PreprocessorError::Directive {
+                span,
+                code,
+                message,
+                expansion,
+            } => Diagnostic::error(span, message)
+                .with_code(code)
+                .with_expansion(expansion.as_ref()),
+        }
+    }
+}
+
+type Result<T> = std::result::Result<T, PreprocessorError>;
+
+fn error<T>(l: &Lexeme, code: &'static str, message: impl Into<String>) -> Result<T> {
+    Err(PreprocessorError::Directive {
+        span: l.span.clone(),
+        code,
+        message: message.into(),
+        expansion: l.expansion.clone(),
+    })
+}
+
+struct Macro {
+    /// Parameter names; `None` for object-like macros.
+    params: Option<Vec<String>>,
+    body: Vec<Lexeme>,
+    defined_at: Span,
+}
+
+impl Macro {
+    fn param(&self, l: &Lexeme) -> Option<usize> {
+        self.params.as_ref()?.iter().position(|p| *p == l.lex)
+    }
+}
+
+/// A lexeme plus the set of macros that must not be expanded again when it
+/// is rescanned.
+#[derive(Clone)]
+struct PpToken {
+    lexeme: Lexeme,
+    hideset: Rc<HashSet<String>>,
+}
+
+struct Conditional {
+    /// Whether the enclosing region is being kept.
+    parent_active: bool,
+    /// Whether the current branch is being kept.
+    active: bool,
+    seen_else: bool,
+    start: Lexeme,
+}
+
+/// Tokens of one file, with macro replacement text pushed in front of the
+/// remaining source tokens.
+struct TokenStream {
+    lexemes: Vec<Lexeme>,
+    pos: usize,
+    /// Replacement tokens waiting to be rescanned, in reverse order.
+    pending: Vec<PpToken>,
+}
+
+impl TokenStream {
+    /// A stream over already-read tokens, such as a macro argument.
+    fn from_tokens(mut tokens: Vec<PpToken>) -> Self {
+        tokens.reverse();
+
+        TokenStream {
+            lexemes: vec![empty_lexeme(TokenType::End, Span::default())],
+            pos: 0,
+            pending: tokens,
+        }
+    }
+
+    fn peek(&self) -> &Lexeme {
+        match self.pending.last() {
+            Some(t) => &t.lexeme,
+            None => &self.lexemes[self.pos],
+        }
+    }
+
+    fn next(&mut self) -> PpToken {
+        if let Some(t) = self.pending.pop() {
+            return t;
+        }
+
+        let lexeme = self.lexemes[self.pos].clone();
+
+        if lexeme.token != TokenType::End {
+            self.pos += 1;
+        }
+
+        PpToken {
+            lexeme,
+            hideset: Rc::default(),
+        }
+    }
+
+    fn at_directive(&self) -> bool {
+        let l = &self.lexemes[self.pos];
+
+        self.pending.is_empty() && l.token == TokenType::Hash && l.line_start
+    }
+
+    /// Consumes the remaining source tokens of the current line.
+    fn rest_of_line(&mut self) -> Vec<Lexeme> {
+        let mut line = Vec::new();
+
+        while !self.lexemes[self.pos].line_start && self.lexemes[self.pos].token != TokenType::End {
+            line.push(self.lexemes[self.pos].clone());
+            self.pos += 1;
+        }
+
+        line
+    }
+}
+
+/// Runs `#include`, `#define`, `#undef`, `#pragma` and conditional
+/// compilation over a source file and its includes, producing the lexeme
+/// stream the parser consumes. Every lexeme keeps the file and line it was
+/// read from; tokens produced by macro expansion are placed at the macro's
+/// use and carry the chain of expansions that produced them.
+///
+/// Macro expansion follows the usual hide-set algorithm: a token is never
+/// replaced by a macro whose expansion it came from.
+pub struct Preprocessor {
+    macros: HashMap<String, Macro>,
+    include_stack: Vec<PathBuf>,
+    /// Files whose whole contents are wrapped in `#ifndef GUARD`.
+    include_guards: HashMap<PathBuf, String>,
+    output: Vec<Lexeme>,
+    warnings: Vec<Diagnostic>,
+    suppressions: Vec<Suppression>,
+}
+
+impl Preprocessor {
+    pub fn new() -> Self {
+        Preprocessor {
+            macros: HashMap::new(),
+            include_stack: Vec::new(),
+            include_guards: HashMap::new(),
+            output: Vec::new(),
+            warnings: Vec::new(),
+            suppressions: Vec::new(),
+        }
+    }
+
+    pub fn preprocess_file(&mut self, path: &str) -> Result<Vec<Lexeme>> {
+        let lexemes = Lexer::lex_file(path)?;
+        let end = self.file(Path::new(path), lexemes)?;
+        self.output.push(end);
+
+        Ok(std::mem::take(&mut self.output))
+    }
+
+    /// Problems found so far that do not stop preprocessing.
+    pub fn warnings(&self) -> &[Diagnostic] {
+        &self.warnings
+    }
+
+    /// Warnings silenced by `#pragma quark suppress`.
+    pub fn suppressions(&self) -> &[Suppression] {
+        &self.suppressions
+    }
+
+    /// Processes one file and returns its `End` lexeme.
+    fn file(&mut self, path: &Path, lexemes: Vec<Lexeme>) -> Result<Lexeme> {
+        let key = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        self.include_stack.push(key.clone());
+
+        let mut stream = TokenStream {
+            lexemes,
+            pos: 0,
+            pending: Vec::new(),
+        };
+        let mut conditionals: Vec<Conditional> = Vec::new();
+
+        // an include guard is an #ifndef opening the file whose #endif closes
+        // it; it is honored while the file is still being read so that guarded
+        // headers may include each other
+        let mut guarded = false;
+        let mut guard_closed_at = None;
+
+        loop {
+            if stream.at_directive() {
+                let hash = stream.next().lexeme;
+                let first_directive = hash.span.start == stream.lexemes[0].span.start;
+                let line = stream.rest_of_line();
+
+                let depth = conditionals.len();
+                self.directive(&hash, &line, &mut conditionals, path)?;
+
+                if first_directive && conditionals.len() == 1 {
+                    if let [d, name] = &line[..] {
+                        if d.lex == "ifndef" {
+                            self.include_guards.insert(key.clone(), name.lex.clone());
+                            guarded = true;
+                        }
+                    }
+                }
+
+                if depth == 1 && conditionals.is_empty() && guard_closed_at.is_none() {
+                    guard_closed_at = Some(stream.pos);
+                }
+
+                continue;
+            }
+
+            let active = conditionals.last().is_none_or(|c| c.active);
+            let t = stream.next();
+
+            if t.lexeme.token == TokenType::End {
+                break;
+            }
+
+            if !active {
+                continue;
+            }
+
+            let mut out = Vec::new();
+            self.expand(t, &mut stream, &mut out)?;
+            self.output.extend(out.into_iter().map(|t| t.lexeme));
+        }
+
+        if let Some(c) = conditionals.first() {
+            return error(&c.start, "Q0011", "Unterminated conditional directive");
+        }
+
+        if guarded && guard_closed_at != Some(stream.lexemes.len() - 1) {
+            self.include_guards.remove(&key);
+        }
+
+        self.include_stack.pop();
+
+        Ok(stream.lexemes.pop().unwrap())
+    }
+
+    fn directive(
+        &mut self,
+        hash: &Lexeme,
+        line: &[Lexeme],
+        conditionals: &mut Vec<Conditional>,
+        path: &Path,
+    ) -> Result<()> {
+        let Some(name) = line.first() else {
+            // the null directive
+            return Ok(());
+        };
+
+        let active = conditionals.last().is_none_or(|c| c.active);
+
+        if !is_identifier(name) {
+            if !active {
+                return Ok(());
+            }
+
+            return error(hash, "Q0010", "Expected preprocessing directive");
+        }
+
+        match name.lex.as_str() {
+            "ifdef" | "ifndef" => {
+                let defined = if active {
+                    let ident = single_identifier(name, line)?;
+                    self.macros.contains_key(&ident.lex)
+                } else {
+                    false
+                };
+
+                conditionals.push(Conditional {
+                    parent_active: active,
+                    active: active && defined == (name.lex == "ifdef"),
+                    seen_else: false,
+                    start: name.clone(),
+                });
+            }
+            "else" => {
+                let Some(c) = conditionals.last_mut() else {
+                    return error(name, "Q0011", "#else without #ifdef");
+                };
+
+                if c.seen_else {
+                    return error(name, "Q0011", "#else after #else");
+                }
+
+                c.seen_else = true;
+                c.active = c.parent_active && !c.active;
+            }
+            "endif" => {
+                if conditionals.pop().is_none() {
+                    return error(name, "Q0011", "#endif without #ifdef");
+                }
+            }
+            _ if !active => {}
+            "define" => self.define(name, line)?,
+            "undef" => {
+                let ident = single_identifier(name, line)?;
+                self.macros.remove(&ident.lex);
+            }
+            "include" => self.include(name, line, path)?,
+            "pragma" => self.pragma(line)?,
+            _ => return error(name, "Q0010", "Unknown preprocessing directive"),
+        }
+
+        Ok(())
+    }
+
+    /// `#pragma quark suppress NAME...` silences the named warnings on the
+    /// line after it. Pragmas for other tools are ignored.
+    fn pragma(&mut self, line: &[Lexeme]) -> Result<()> {
+        let [directive, namespace, rest @ ..] = line else {
+            return Ok(());
+        };
+
+        if namespace.lex != "quark" {
+            return Ok(());
+        }
+
+        let Some((command, names)) = rest.split_first() else {
+            return error(directive, "Q0016", "Expected quark pragma");
+        };
+
+        if command.lex != "suppress" {
+            return error(command, "Q0016", "Unknown quark pragma");
+        }
+
+        if names.is_empty() {
+            return error(command, "Q0016", "Expected warning name");
+        }
+
+        // names such as unused-variable are lexed as several tokens
+        let mut words: Vec<(&Lexeme, String)> = Vec::new();
+        let mut end = None;
+
+        for l in names {
+            match words.last_mut() {
+                Some((_, word)) if end == Some(l.span.start) => word.push_str(&l.lex),
+                _ => words.push((l, l.lex.clone())),
+            }
+
+            end = Some(l.span.end);
+        }
+
+        for (l, word) in words {
+            let Some(warning) = Warning::from_name(&word) else {
+                return error(l, "Q0016", format!("Unknown warning {}", word));
+            };
+
+            self.suppressions.push(Suppression {
+                file: l.span.infile_name.clone(),
+                line: l.span.lineno + 1,
+                warning,
+            });
+        }
+
+        Ok(())
+    }
+
+    fn define(&mut self, directive: &Lexeme, line: &[Lexeme]) -> Result<()> {
+        let Some(name) = line.get(1).filter(|l| is_identifier(l)) else {
+            return error(directive, "Q0012", "Expected macro name");
+        };
+
+        // a function-like macro has its '(' directly after the name
+        let (params, body) = match line.get(2) {
+            Some(l) if l.token == TokenType::LPar && l.span.start == name.span.end => {
+                let (params, rest) = macro_params(l, &line[3..])?;
+                (Some(params), rest)
+            }
+            _ => (None, &line[2..]),
+        };
+
+        let m = Macro {
+            params,
+            body: body.to_vec(),
+            defined_at: name.span.clone(),
+        };
+
+        for (i, l) in m.body.iter().enumerate() {
+            if l.token == TokenType::DHash && (i == 0 || i == m.body.len() - 1) {
+                return error(
+                    l,
+                    "Q0012",
+                    "'##' cannot appear at either end of a macro expansion",
+                );
+            }
+
+            if l.token == TokenType::Hash
+                && m.params.is_some()
+                && m.body.get(i + 1).and_then(|p| m.param(p)).is_none()
+            {
+                return error(l, "Q0012", "'#' is not followed by a macro parameter");
+            }
+        }
+
+        if let Some(prev) = self.macros.get(&name.lex) {
+            if prev.params != m.params || !same_tokens(&prev.body, &m.body) {
+                let warning = Diagnostic::warning(
+                    Warning::MacroRedefined,
+                    name.span.clone(),
+                    format!("macro {} redefined", name.lex),
+                )
+                .with_label(prev.defined_at.clone(), "previous definition here");
+
+                self.warnings.push(warning);
+            }
+        }
+
+        self.macros.insert(name.lex.clone(), m);
+
+        Ok(())
+    }
+
+    fn include(&mut self, directive: &Lexeme, line: &[Lexeme], path: &Path) -> Result<()> {
+        let file = match &line[1..] {
+            [file] if file.token == TokenType::StrLit => file,
+            [file, ..] if file.token == TokenType::Lt => {
+                return error(file, "Q0014", "Only quoted #include \"file\" is supported")
+            }
+            _ => return error(directive, "Q0014", "Expected \"file\" after #include"),
+        };
+
+        if self.include_stack.len() >= MAX_INCLUDE_DEPTH {
+            return error(
+                file,
+                "Q0015",
+                format!("#include nested more than {} levels", MAX_INCLUDE_DEPTH),
+            );
+        }
+
+        let name = &file.lex[1..file.lex.len() - 1];
+        let target = path.parent().unwrap_or(Path::new("")).join(name);
+        let key = fs::canonicalize(&target).unwrap_or_else(|_| target.clone());
+
+        if let Some(guard) = self.include_guards.get(&key) {
+            if self.macros.contains_key(guard) {
+                return Ok(());
+            }
+        }
+
+        if self.include_stack.contains(&key) {
+            return error(file, "Q0015", format!("Circular #include of {}", name));
+        }
+
+        let source = match fs::read_to_string(&target) {
+            Ok(source) => source,
+            Err(err) => {
+                return error(
+                    file,
+                    "Q0001",
+                    format!("Couldn't open included file: {}", err),
+                )
+            }
+        };
+
+        let lexemes = Lexer::new(&target.to_string_lossy(), &source).lex()?;
+        self.file(&target, lexemes)?;
+
+        Ok(())
+    }
+
+    /// Expands `t` if it names a macro, pushing the replacement back onto
+    /// `stream` to be rescanned; anything else is appended to `out`.
+    fn expand(&self, t: PpToken, stream: &mut TokenStream, out: &mut Vec<PpToken>) -> Result<()> {
+        let name = &t.lexeme.lex;
+
+        let m = match self.macros.get(name) {
+            Some(m) if is_identifier(&t.lexeme) && !t.hideset.contains(name) => m,
+            _ => {
+                out.push(t);
+                return Ok(());
+            }
+        };
+
+        let (mut hideset, args) = match &m.params {
+            None => ((*t.hideset).clone(), Vec::new()),
+            Some(params) => {
+                // a function-like macro name not followed by '(' is left alone
+                if stream.peek().token != TokenType::LPar {
+                    out.push(t);
+                    return Ok(());
+                }
+
+                let (args, rpar) = macro_args(&t.lexeme, stream)?;
+
+                if args.len() != params.len()
+                    && !(params.is_empty() && args.len() == 1 && args[0].is_empty())
+                {
+                    return error(
+                        &t.lexeme,
+                        "Q0013",
+                        format!(
+                            "Macro {} requires {} arguments, but {} given",
+                            name,
+                            params.len(),
+                            args.len()
+                        ),
+                    );
+                }
+
+                let hideset = t.hideset.intersection(&rpar.hideset).cloned().collect();
+                (hideset, args)
+            }
+        };
+
+        hideset.insert(name.clone());
+        let hideset = Rc::new(hideset);
+
+        let expansion = Rc::new(Expansion {
+            name: name.clone(),
+            defined_at: m.defined_at.clone(),
+            invoked_at: t.lexeme.span.clone(),
+            parent: t.lexeme.expansion.clone(),
+        });
+
+        let replacement = self.substitute(m, &args, &t.lexeme, &expansion)?;
+
+        for mut r in replacement.into_iter().rev() {
+            r.hideset = if r.hideset.is_empty() {
+                hideset.clone()
+            } else {
+                Rc::new(r.hideset.union(&hideset).cloned().collect())
+            };
+
+            stream.pending.push(r);
+        }
+
+        Ok(())
+    }
+
+    /// Fully macro-expands a macro argument on its own.
+    fn expand_all(&self, tokens: Vec<PpToken>) -> Result<Vec<PpToken>> {
+        let mut stream = TokenStream::from_tokens(tokens);
+        let mut out = Vec::new();
+
+        loop {
+            let t = stream.next();
+
+            if t.lexeme.token == TokenType::End {
+                return Ok(out);
+            }
+
+            self.expand(t, &mut stream, &mut out)?;
+        }
+    }
+
+    /// Replaces the parameters in the body of `m` with `args`, applying `#`
+    /// and `##`. Tokens taken from the body are placed at `invocation`.
+    fn substitute(
+        &self,
+        m: &Macro,
+        args: &[Vec<PpToken>],
+        invocation: &Lexeme,
+        expansion: &Rc<Expansion>,
+    ) -> Result<Vec<PpToken>> {
+        let from_body = |l: &Lexeme| PpToken {
+            lexeme: Lexeme {
+                span: invocation.span.clone(),
+                line_start: false,
+                expansion: Some(expansion.clone()),
+                ..l.clone()
+            },
+            hideset: Rc::default(),
+        };
+
+        // stands in for an empty argument next to '##'
+        let placemarker = || PpToken {
+            lexeme: empty_lexeme(TokenType::End, invocation.span.clone()),
+            hideset: Rc::default(),
+        };
+
+        let body = &m.body;
+        let mut out: Vec<PpToken> = Vec::new();
+        let mut i = 0;
+
+        while i < body.len() {
+            let l = &body[i];
+
+            if l.token == TokenType::Hash && m.params.is_some() {
+                let arg = &args[m.param(&body[i + 1]).unwrap()];
+                let mut t = from_body(l);
+                t.lexeme.token = TokenType::StrLit;
+                t.lexeme.lex = stringify(arg);
+
+                out.push(t);
+                i += 2;
+                continue;
+            }
+
+            if l.token == TokenType::DHash {
+                let rhs = &body[i + 1];
+                let mut operand = match m.param(rhs) {
+                    Some(p) => args[p].clone(),
+                    None => vec![from_body(rhs)],
+                };
+
+                if operand.is_empty() {
+                    operand.push(placemarker());
+                }
+
+                let mut operand = operand.into_iter();
+                let first = operand.next().unwrap();
+
+                let pasted = match out.pop() {
+                    Some(lhs) => paste(lhs, first, expansion)?,
+                    None => first,
+                };
+
+                out.push(pasted);
+                out.extend(operand);
+                i += 2;
+                continue;
+            }
+
+            if let Some(p) = m.param(l) {
+                // operands of '##' are used as written, everything else is
+                // expanded before substitution
+                if body.get(i + 1).is_some_and(|n| n.token == TokenType::DHash) {
+                    if args[p].is_empty() {
+                        out.push(placemarker());
+                    }
+
+                    out.extend(args[p].iter().cloned());
+                } else {
+                    out.extend(self.expand_all(args[p].clone())?);
+                }
+
+                i += 1;
+                continue;
+            }
+
+            out.push(from_body(l));
+            i += 1;
+        }
+
+        out.retain(|t| !t.lexeme.lex.is_empty());
+
+        Ok(out)
+    }
+}
+
+fn empty_lexeme(token: TokenType, span: Span) -> Lexeme {
+    Lexeme {
+        token,
+        lex: String::new(),
+        span,
+        line_start: false,
+        expansion: None,
+    }
+}
+
+/// Parses the parameter list of a function-like macro definition, returning
+/// the names and the tokens after the closing ')'.
+fn macro_params<'a>(lpar: &Lexeme, line: &'a [Lexeme]) -> Result<(Vec<String>, &'a [Lexeme])> {
+    let mut params: Vec<String> = Vec::new();
+    let mut i = 0;
+
+    if line.first().is_some_and(|l| l.token == TokenType::RPar) {
+        return Ok((params, &line[1..]));
+    }
+
+    loop {
+        let Some(param) = line.get(i).filter(|l| is_identifier(l)) else {
+            return error(
+                line.get(i).unwrap_or(lpar),
+                "Q0012",
+                "Expected macro parameter name",
+            );
+        };
+
+        if params.contains(&param.lex) {
+            return error(param, "Q0012", "Duplicate macro parameter");
+        }
+
+        params.push(param.lex.clone());
+
+        match line.get(i + 1).map(|l| l.token) {
+            Some(TokenType::Comma) => i += 2,
+            Some(TokenType::RPar) => return Ok((params, &line[i + 2..])),
+            _ => {
+                return error(
+                    line.get(i + 1).unwrap_or(param),
+                    "Q0012",
+                    "Expected ')' in macro parameter list",
+                )
+            }
+        }
+    }
+}
+
+/// Reads the parenthesized arguments of a function-like macro invocation,
+/// returning them along with the closing ')'.
+fn macro_args(name: &Lexeme, stream: &mut TokenStream) -> Result<(Vec<Vec<PpToken>>, PpToken)> {
+    let mut args = vec![Vec::new()];
+    let mut depth = 0;
+
+    stream.next();
+
+    loop {
+        let t = stream.next();
+
+        match t.lexeme.token {
+            TokenType::End => {
+                return error(
+                    name,
+                    "Q0013",
+                    format!("Unterminated argument list invoking macro {}", name.lex),
+                )
+            }
+            TokenType::RPar if depth == 0 => return Ok((args, t)),
+            TokenType::Comma if depth == 0 => {
+                args.push(Vec::new());
+                continue;
+            }
+            TokenType::LPar => depth += 1,
+            TokenType::RPar => depth -= 1,
+            _ => {}
+        }
+
+        args.last_mut().unwrap().push(t);
+    }
+}
+
+/// Spells a macro argument as a string literal, keeping a single space
+/// wherever the source had whitespace between tokens.
+fn stringify(arg: &[PpToken]) -> String {
+    let mut s = String::from("\"");
+
+    for (i, t) in arg.iter().enumerate() {
+        let l = &t.lexeme;
+
+        if i > 0 {
+            let prev = &arg[i - 1].lexeme.span;
+
+            if prev.infile_name != l.span.infile_name || prev.end != l.span.start {
+                s.push(' ');
+            }
+        }
+
+        if matches!(l.token, TokenType::StrLit | TokenType::CharLit) {
+            for c in l.lex.chars() {
+                if c == '"' || c == '\\' {
+                    s.push('\\');
+                }
+
+                s.push(c);
+            }
+        } else {
+            s.push_str(&l.lex);
+        }
+    }
+
+    s.push('"');
+    s
+}
+
+/// Joins two tokens with '##'. The result must lex as exactly one token.
+fn paste(lhs: PpToken, rhs: PpToken, expansion: &Rc<Expansion>) -> Result<PpToken> {
+    if lhs.lexeme.lex.is_empty() {
+        return Ok(rhs);
+    }
+
+    if rhs.lexeme.lex.is_empty() {
+        return Ok(lhs);
+    }
+
+    let text = format!("{}{}", lhs.lexeme.lex, rhs.lexeme.lex);
+    let lexemes = Lexer::new(&lhs.lexeme.span.infile_name, &text).lex();
+
+    let token = match lexemes.as_deref() {
+        Ok([l, end]) if end.token == TokenType::End => l.token,
+        _ => {
+            let at = Lexeme {
+                expansion: Some(expansion.clone()),
+                ..lhs.lexeme.clone()
+            };
+
+            return error(
+                &at,
+                "Q0013",
+                format!(
+                    "Pasting \"{}\" and \"{}\" does not give a valid preprocessing token",
+                    lhs.lexeme.lex, rhs.lexeme.lex
+                ),
+            );
+        }
+    };
+
+    Ok(PpToken {
+        lexeme: Lexeme {
+            token,
+            lex: text,
+            ..lhs.lexeme
+        },
+        hideset: Rc::new(lhs.hideset.intersection(&rhs.hideset).cloned().collect()),
+    })
+}
+
+fn is_identifier(l: &Lexeme) -> bool {
+    // keywords are valid macro names too
+    l.lex
+        .bytes()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == b'_')
+}
+
+fn single_identifier<'a>(directive: &Lexeme, line: &'a [Lexeme]) -> Result<&'a Lexeme> {
+    match &line[1..] {
+        [ident] if is_identifier(ident) => Ok(ident),
+        [] => error(directive, "Q0012", "Expected macro name"),
+        [_, extra, ..] if is_identifier(&line[1]) => {
+            error(extra, "Q0012", "Extra tokens after macro name")
+        }
+        [other, ..] => error(other, "Q0012", "Expected macro name"),
+    }
+}
+
+fn same_tokens(a: &[Lexeme], b: &[Lexeme]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).all(|(x, y)| x.lex == y.lex)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn preprocess(src: &str) -> Result<Vec<Lexeme>> {
+        let mut preprocessor = Preprocessor::new();
+        let lexemes = Lexer::new("test.c", src).lex()?;
+        preprocessor.file(Path::new("test.c"), lexemes)?;
+
+        Ok(preprocessor.output)
+    }
+
+    fn text(src: &str) -> String {
+        let lexemes = preprocess(src).expect("input preprocesses");
+        let words: Vec<&str> = lexemes.iter().map(|l| l.lex.as_str()).collect();
+        words.join(" ")
+    }
+
+    fn error_code(src: &str) -> &'static str {
+        match preprocess(src).expect_err("input is invalid") {
+            PreprocessorError::Lex(err) => err.code,
+            PreprocessorError::Directive { code, .. } => code,
+        }
+    }
+
+    #[test]
+    fn object_like_macro() {
+        assert_eq!(text("#define N 10\nint a[N];"), "int a [ 10 ] ;");
+        assert_eq!(text("#define N 10\n#undef N\nN"), "N");
+    }
+
+    #[test]
+    fn function_like_macro() {
+        let src = "#define MAX(a, b) ((a) > (b) ? (a) : (b))\nMAX(x, 1)";
+
+        assert_eq!(text(src), "( ( x ) > ( 1 ) ? ( x ) : ( 1 ) )");
+    }
+
+    #[test]
+    fn expansion_is_recorded() {
+        let lexemes = preprocess("#define ONE 1\n#define TWO ONE + ONE\nTWO").unwrap();
+        let one = lexemes[0].expansion.as_ref().expect("came from a macro");
+        let names: Vec<&str> = one.backtrace().map(|e| e.name.as_str()).collect();
+
+        assert_eq!(names, ["ONE", "TWO"]);
+        assert_eq!(one.defined_at.lineno, 1);
+        // tokens from a macro body have the span of the outermost invocation
+        assert_eq!(one.invoked_at.lineno, 3);
+    }
+
+    #[test]
+    fn macro_is_not_expanded_inside_itself() {
+        assert_eq!(text("#define X X + 1\nX"), "X + 1");
+    }
+
+    #[test]
+    fn stringify_and_paste() {
+        let src = "#define S(x) #x\n#define CAT(a, b) a ## b\nS(hi) CAT(put, int)";
+
+        assert_eq!(text(src), "\"hi\" putint");
+    }
+
+    #[test]
+    fn conditionals() {
+        let src = "#define A\n#ifdef A\nyes\n#else\nno\n#endif\n#ifndef A\nno\n#endif";
+
+        assert_eq!(text(src), "yes");
+    }
+
+    #[test]
+    fn suppressions() {
+        let mut preprocessor = Preprocessor::new();
+        let lexemes = Lexer::new("test.c", "#pragma quark suppress unused-variable\n")
+            .lex()
+            .unwrap();
+        preprocessor.file(Path::new("test.c"), lexemes).unwrap();
+
+        let [s] = preprocessor.suppressions() else {
+            panic!("one suppression expected");
+        };
+        assert_eq!((s.line, s.warning), (2, Warning::UnusedVariable));
+    }
+
+    #[test]
+    fn include() {
+        let dir = std::env::temp_dir().join(format!("quark-include-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("n.h"),
+            "#ifndef N_H\n#define N_H\n#define N 3\n#endif\n",
+        )
+        .unwrap();
+        fs::write(dir.join("main.c"), "#include \"n.h\"\n#include \"n.h\"\nN").unwrap();
+
+        let lexemes = Preprocessor::new()
+            .preprocess_file(dir.join("main.c").to_str().unwrap())
+            .unwrap();
+        let words: Vec<&str> = lexemes.iter().map(|l| l.lex.as_str()).collect();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(words, ["3", ""]);
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(error_code("#bogus\n"), "Q0010");
+        assert_eq!(error_code("#ifdef A\n"), "Q0011");
+        assert_eq!(error_code("#endif\n"), "Q0011");
+        assert_eq!(error_code("#define\n"), "Q0012");
+        assert_eq!(error_code("#define F(a, a) a\n"), "Q0012");
+        assert_eq!(error_code("#include <stdio.h>\n"), "Q0014");
+        assert_eq!(error_code("#pragma quark shout\n"), "Q0016");
+        assert_eq!(error_code("#pragma quark suppress loud\n"), "Q0016");
+    }
+}
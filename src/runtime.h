@@ -0,0 +1,13 @@
+/* Runtime library available to every program. Further functions can be
+ * declared in headers passed with --runtime. */
+
+int getchar(void);
+int putchar(int c);
+
+int getint(void);
+void putint(int value);
+
+float getfloat(void);
+void putfloat(float value);
+
+void putstring(char s[]);
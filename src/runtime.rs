@@ -0,0 +1,115 @@
+use crate::diagnostic::Diagnostic;
+use crate::lexer::{Lexeme, Lexer};
+use crate::parser::{self, Decl, FunctionDef};
+use crate::preprocessor::Preprocessor;
+
+/// File name diagnostics give for the bundled header.
+pub const BUNDLED_NAME: &str = "<runtime>";
+pub const BUNDLED: &str = include_str!("runtime.h");
+
+/// Signatures of the functions a program may call without declaring them,
+/// read from prototypes in C headers: the bundled `runtime.h` and any added
+/// with `--runtime`.
+pub struct Runtime {
+    pub functions: Vec<FunctionDef>,
+}
+
+impl Runtime {
+    pub fn bundled() -> Runtime {
+        let lexemes = Lexer::new(BUNDLED_NAME, BUNDLED)
+            .lex()
+            .expect("bundled runtime header is valid");
+
+        let mut runtime = Runtime {
+            functions: Vec::new(),
+        };
+
+        runtime
+            .add(lexemes)
+            .expect("bundled runtime header is valid");
+
+        runtime
+    }
+
+    /// Adds the prototypes in the header at `path`.
+    pub fn load(&mut self, path: &str) -> Result<(), Vec<Diagnostic>> {
+        let lexemes = Preprocessor::new()
+            .preprocess_file(path)
+            .map_err(|err| vec![err.into()])?;
+
+        self.add(lexemes)
+    }
+
+    fn add(&mut self, lexemes: Vec<Lexeme>) -> Result<(), Vec<Diagnostic>> {
+        let (program, errors) = parser::Parser::new(lexemes).parse();
+
+        if !errors.is_empty() {
+            return Err(errors.into_iter().map(Diagnostic::from).collect());
+        }
+
+        for decl in program.decls {
+            let (span, what) = match decl {
+                Decl::Function(f) if f.body.is_none() => {
+                    self.functions.push(f);
+                    continue;
+                }
+                Decl::Function(f) => (f.span, format!("function {}", f.name)),
+                Decl::Struct(def) => (def.span, format!("struct {}", def.name)),
+                Decl::Var(vars) => (vars[0].span.clone(), format!("variable {}", vars[0].name)),
+            };
+
+            let error = Diagnostic::error(span, format!("runtime header defines {}", what))
+                .with_code("Q0601")
+                .with_note("only function prototypes are allowed in runtime headers");
+            return Err(vec![error]);
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    /// Loads `src` as a header on top of the bundled one.
+    fn load(name: &str, src: &str) -> Result<Runtime, Vec<Diagnostic>> {
+        let path = std::env::temp_dir().join(format!("quark-{}-{}.h", std::process::id(), name));
+        fs::write(&path, src).unwrap();
+
+        let mut runtime = Runtime::bundled();
+        let result = runtime.load(path.to_str().unwrap());
+        fs::remove_file(&path).unwrap();
+
+        result.map(|()| runtime)
+    }
+
+    fn names(runtime: &Runtime) -> Vec<&str> {
+        runtime.functions.iter().map(|f| f.name.as_str()).collect()
+    }
+
+    #[test]
+    fn bundled_header() {
+        let runtime = Runtime::bundled();
+
+        for name in ["getchar", "putchar", "getint", "putint", "putstring"] {
+            assert!(names(&runtime).contains(&name), "{} is missing", name);
+        }
+    }
+
+    #[test]
+    fn added_header() {
+        let runtime = load("added", "#define N 4\nint sum(int a[], int n);\n").unwrap();
+
+        assert_eq!(names(&runtime).last(), Some(&"sum"));
+    }
+
+    #[test]
+    fn only_prototypes_are_allowed() {
+        let errors = load("definition", "int g;\n").err().unwrap();
+
+        assert_eq!(errors[0].message, "runtime header defines variable g");
+        assert!(load("syntax", "int f(\n").is_err());
+    }
+}
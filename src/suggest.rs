@@ -0,0 +1,104 @@
+/// Number of single-character insertions, deletions, substitutions and
+/// swaps of adjacent characters that turn `a` into `b`.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+
+    // d[i][j] is the distance between the first i characters of a and the
+    // first j of b
+    let mut d = vec![vec![0; b.len() + 1]; a.len() + 1];
+
+    for (i, row) in d.iter_mut().enumerate() {
+        row[0] = i;
+    }
+
+    for (j, cell) in d[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = (a[i - 1] != b[j - 1]) as usize;
+            d[i][j] = (d[i - 1][j - 1] + cost)
+                .min(d[i - 1][j] + 1)
+                .min(d[i][j - 1] + 1);
+
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d[i][j] = d[i][j].min(d[i - 2][j - 2] + 1);
+            }
+        }
+    }
+
+    d[a.len()][b.len()]
+}
+
+/// `name` without case or underscores, so that `put_int` and `PutInt` both
+/// match `putint`.
+fn normalize(name: &str) -> String {
+    name.chars()
+        .filter(|&c| c != '_')
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+/// The candidate `name` was most likely meant to be: one that differs only
+/// in case and underscores, or failing that the nearest by edit distance,
+/// allowing one edit per three characters. `None` when nothing is close or
+/// two candidates are equally close.
+pub fn closest<'a>(name: &str, candidates: impl IntoIterator<Item = &'a str>) -> Option<&'a str> {
+    let normalized = normalize(name);
+    let limit = (name.chars().count() / 3).max(1);
+
+    let mut best: Option<(usize, &str)> = None;
+    let mut tied = false;
+
+    for candidate in candidates {
+        if candidate == name {
+            continue;
+        }
+
+        let distance = if normalize(candidate) == normalized {
+            0
+        } else {
+            edit_distance(name, candidate)
+        };
+
+        if distance > limit {
+            continue;
+        }
+
+        match best {
+            Some((d, c)) if distance == d && candidate != c => tied = true,
+            Some((d, _)) if distance >= d => {}
+            _ => {
+                best = Some((distance, candidate));
+                tied = false;
+            }
+        }
+    }
+
+    best.filter(|_| !tied).map(|(_, c)| c)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn distances() {
+        assert_eq!(edit_distance("count", "count"), 0);
+        assert_eq!(edit_distance("conut", "count"), 1);
+        assert_eq!(edit_distance("cont", "count"), 1);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+    }
+
+    #[test]
+    fn closest_names() {
+        let names = ["count", "total", "put_int", "a", "b"];
+
+        assert_eq!(closest("conut", names), Some("count"));
+        assert_eq!(closest("PutInt", names), Some("put_int"));
+        assert_eq!(closest("c", names), None);
+        assert_eq!(closest("price", names), None);
+    }
+}
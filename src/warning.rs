@@ -0,0 +1,240 @@
+use std::collections::HashSet;
+use std::rc::Rc;
+
+use crate::diagnostic::{Diagnostic, Severity};
+
+/// The kinds of warning, each of which may be enabled, disabled or turned
+/// into an error by name with `-W`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Warning {
+    UnusedVariable,
+    UnusedParameter,
+    Shadow,
+    ImplicitNarrowing,
+    UnreachableCode,
+    EmptyBody,
+    AssignInCondition,
+    MissingReturn,
+    ArrayBounds,
+    MacroRedefined,
+}
+
+impl Warning {
+    pub const ALL: [Warning; 10] = [
+        Warning::UnusedVariable,
+        Warning::UnusedParameter,
+        Warning::Shadow,
+        Warning::ImplicitNarrowing,
+        Warning::UnreachableCode,
+        Warning::EmptyBody,
+        Warning::AssignInCondition,
+        Warning::MissingReturn,
+        Warning::ArrayBounds,
+        Warning::MacroRedefined,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Warning::UnusedVariable => "unused-variable",
+            Warning::UnusedParameter => "unused-parameter",
+            Warning::Shadow => "shadow",
+            Warning::ImplicitNarrowing => "implicit-narrowing",
+            Warning::UnreachableCode => "unreachable-code",
+            Warning::EmptyBody => "empty-body",
+            Warning::AssignInCondition => "assign-in-condition",
+            Warning::MissingReturn => "missing-return",
+            Warning::ArrayBounds => "array-bounds",
+            Warning::MacroRedefined => "macro-redefined",
+        }
+    }
+
+    /// The diagnostic code, which `--explain` describes.
+    pub fn code(self) -> &'static str {
+        match self {
+            Warning::UnusedVariable => "Q0901",
+            Warning::UnusedParameter => "Q0902",
+            Warning::Shadow => "Q0903",
+            Warning::ImplicitNarrowing => "Q0904",
+            Warning::UnreachableCode => "Q0905",
+            Warning::EmptyBody => "Q0906",
+            Warning::AssignInCondition => "Q0907",
+            Warning::MissingReturn => "Q0908",
+            Warning::ArrayBounds => "Q0909",
+            Warning::MacroRedefined => "Q0910",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Warning> {
+        Warning::ALL.into_iter().find(|w| w.name() == name)
+    }
+
+    /// Unused parameters are common in functions written to fit a
+    /// signature, so they are only reported on request.
+    fn enabled_by_default(self) -> bool {
+        self != Warning::UnusedParameter
+    }
+}
+
+/// A `#pragma quark suppress` naming `warning`, which silences it on the
+/// line after the pragma.
+#[derive(Clone, Debug)]
+pub struct Suppression {
+    pub file: Rc<str>,
+    pub line: usize,
+    pub warning: Warning,
+}
+
+/// Which warnings are reported, and which of those are errors.
+pub struct WarningOptions {
+    enabled: HashSet<Warning>,
+    errors: HashSet<Warning>,
+    all_errors: bool,
+}
+
+impl Default for WarningOptions {
+    fn default() -> Self {
+        WarningOptions {
+            enabled: Warning::ALL
+                .into_iter()
+                .filter(|w| w.enabled_by_default())
+                .collect(),
+            errors: HashSet::new(),
+            all_errors: false,
+        }
+    }
+}
+
+impl WarningOptions {
+    /// Applies one `-W` option: `NAME` or `no-NAME` to enable or disable a
+    /// warning, `error=NAME` or `no-error=NAME` to make it an error or not,
+    /// `error` to make every warning an error, and `all` or `no-all` to
+    /// enable or disable every warning.
+    pub fn set(&mut self, option: &str) -> Result<(), String> {
+        let lookup = |name: &str| {
+            Warning::from_name(name).ok_or_else(|| format!("Unknown warning option -W{}", option))
+        };
+
+        match option {
+            "error" => self.all_errors = true,
+            "no-error" => self.all_errors = false,
+            "all" => self.enabled.extend(Warning::ALL),
+            "no-all" => self.enabled.clear(),
+            _ => {
+                if let Some(name) = option.strip_prefix("error=") {
+                    let w = lookup(name)?;
+                    self.enabled.insert(w);
+                    self.errors.insert(w);
+                } else if let Some(name) = option.strip_prefix("no-error=") {
+                    self.errors.remove(&lookup(name)?);
+                } else if let Some(name) = option.strip_prefix("no-") {
+                    self.enabled.remove(&lookup(name)?);
+                } else {
+                    self.enabled.insert(lookup(option)?);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// `d` as it should be reported: `None` for a warning that is disabled
+    /// or suppressed on its line, and an error for one made into an error.
+    pub fn apply(&self, d: &Diagnostic, suppressions: &[Suppression]) -> Option<Diagnostic> {
+        let Some(w) = d.warning else {
+            return Some(d.clone());
+        };
+
+        let suppressed = suppressions
+            .iter()
+            .any(|s| s.warning == w && s.line == d.span.lineno && s.file == d.span.infile_name);
+
+        if !self.enabled.contains(&w) || suppressed {
+            return None;
+        }
+
+        let mut d = d.clone();
+
+        if self.all_errors || self.errors.contains(&w) {
+            d.severity = Severity::Error;
+        }
+
+        Some(d)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::Span;
+
+    fn warning(w: Warning, line: usize) -> Diagnostic {
+        let span = Span {
+            infile_name: "test.c".into(),
+            lineno: line,
+            col: 1,
+            end_lineno: line,
+            end_col: 2,
+            start: 0,
+            end: 1,
+        };
+
+        Diagnostic::warning(w, span, "warning")
+    }
+
+    fn severity(options: &WarningOptions, w: Warning) -> Option<Severity> {
+        options.apply(&warning(w, 1), &[]).map(|d| d.severity)
+    }
+
+    #[test]
+    fn options() {
+        let mut options = WarningOptions::default();
+
+        assert_eq!(severity(&options, Warning::Shadow), Some(Severity::Warning));
+        assert_eq!(severity(&options, Warning::UnusedParameter), None);
+
+        options.set("unused-parameter").unwrap();
+        options.set("no-shadow").unwrap();
+        options.set("error=array-bounds").unwrap();
+
+        assert_eq!(
+            severity(&options, Warning::UnusedParameter),
+            Some(Severity::Warning)
+        );
+        assert_eq!(severity(&options, Warning::Shadow), None);
+        assert_eq!(
+            severity(&options, Warning::ArrayBounds),
+            Some(Severity::Error)
+        );
+
+        options.set("error").unwrap();
+        assert_eq!(
+            severity(&options, Warning::EmptyBody),
+            Some(Severity::Error)
+        );
+
+        assert_eq!(
+            options.set("loud").unwrap_err(),
+            "Unknown warning option -Wloud"
+        );
+    }
+
+    #[test]
+    fn suppressions_apply_to_their_line() {
+        let suppressions = [Suppression {
+            file: "test.c".into(),
+            line: 2,
+            warning: Warning::UnusedVariable,
+        }];
+        let options = WarningOptions::default();
+
+        assert!(options
+            .apply(&warning(Warning::UnusedVariable, 2), &suppressions)
+            .is_none());
+        assert!(options
+            .apply(&warning(Warning::UnusedVariable, 3), &suppressions)
+            .is_some());
+        assert!(options
+            .apply(&warning(Warning::Shadow, 2), &suppressions)
+            .is_some());
+    }
+}
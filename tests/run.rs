@@ -0,0 +1,211 @@
+//! Runs programs with `quark run` and checks what they write and how they
+//! exit.
+
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Output, Stdio};
+
+/// Writes `src` to a file named after `name` and runs it with `quark run`,
+/// followed by `args`, feeding it `input`.
+fn quark_run(name: &str, src: &str, args: &[&str], input: &str) -> Output {
+    let path: PathBuf =
+        env::temp_dir().join(format!("quark-run-{}-{}.c", std::process::id(), name));
+    fs::write(&path, src).unwrap();
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_quark"))
+        .arg("run")
+        .arg(&path)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    fs::remove_file(&path).unwrap();
+
+    output
+}
+
+fn stdout(output: &Output) -> &str {
+    std::str::from_utf8(&output.stdout).unwrap()
+}
+
+fn stderr(output: &Output) -> &str {
+    std::str::from_utf8(&output.stderr).unwrap()
+}
+
+/// Asserts that `src` is rejected with `code`, without panicking.
+fn assert_rejected(name: &str, src: &str, code: &str) {
+    let output = quark_run(name, src, &[], "");
+
+    assert_eq!(output.status.code(), Some(1), "{}", stderr(&output));
+    assert!(
+        stderr(&output).contains(&format!("error[{}]", code)),
+        "{}",
+        stderr(&output)
+    );
+}
+
+#[test]
+fn output_and_exit_status() {
+    let output = quark_run(
+        "hello",
+        "int main() { putstring(\"hello \"); putint(6 * 7); return 3; }",
+        &[],
+        "",
+    );
+
+    assert_eq!(stdout(&output), "hello 42");
+    assert_eq!(output.status.code(), Some(3));
+}
+
+#[test]
+fn echo() {
+    let src = "int main() {\n\
+                   int c = getchar();\n\
+                   while (c != -1) { putchar(c); c = getchar(); }\n\
+                   return 0;\n\
+               }";
+    let output = quark_run("echo", src, &[], "one\ntwo\n");
+
+    assert_eq!(stdout(&output), "one\ntwo\n");
+    assert_eq!(output.status.code(), Some(0));
+}
+
+#[test]
+fn runtime_error() {
+    let src = "int main() { int z = 0; putint(1); return 1 / z; }";
+    let output = quark_run("fault", src, &[], "");
+
+    assert_eq!(stdout(&output), "1");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr(&output).contains("error[Q0701]: division by zero"));
+}
+
+#[test]
+fn runaway_recursion() {
+    let output = quark_run(
+        "recursion",
+        "int f(int n) { return f(n + 1); }\nint main() { return f(0); }",
+        &[],
+        "",
+    );
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr(&output).contains("error[Q0704]"));
+}
+
+#[test]
+fn variable_in_its_own_initializer() {
+    let output = quark_run(
+        "self",
+        "int main() { int y = y; putint(y); return 0; }",
+        &[],
+        "",
+    );
+
+    assert_eq!(stdout(&output), "0");
+    assert_eq!(output.status.code(), Some(0));
+}
+
+#[test]
+fn main_with_parameters() {
+    assert_rejected("argc", "int main(int argc) { return 0; }", "Q0705");
+}
+
+#[test]
+fn main_returning_a_struct() {
+    let src = "struct S { int a; };\nstruct S main() { struct S s; s.a = 1; return s; }";
+
+    assert_rejected("struct-main", src, "Q0705");
+}
+
+#[test]
+fn global_in_its_own_initializer() {
+    assert_rejected(
+        "global-self",
+        "int g = g + 1;\nint main() { return g; }",
+        "Q0209",
+    );
+}
+
+#[test]
+fn global_initialized_by_a_call() {
+    let src = "int f(); int g = f(); int h = 5; int f() { return h; }\nint main() { return g; }";
+
+    assert_rejected("global-call", src, "Q0209");
+}
+
+#[test]
+fn shadowed_struct_passed_as_the_global_one() {
+    let src = "struct P { int a; int b; };\n\
+               int sum(struct P p) { return p.a + p.b; }\n\
+               int main() { struct P { int x; }; struct P q; q.x = 1; return sum(q); }";
+
+    assert_rejected("shadow", src, "Q0313");
+}
+
+#[test]
+fn misspelt_member_from_a_macro() {
+    let src = "struct P { int x; };\n\
+               #define GET(s) s.xxxxxxxxxx\n\
+               int main() { struct P p;\n\
+               GET(p);\n\
+               return 0; }";
+
+    assert_rejected("macro-member", src, "Q0309");
+}
+
+#[test]
+fn options_after_run() {
+    let src = "int main() { int x = 2.5; putint(x); return x; }";
+
+    let output = quark_run("werror", src, &["-W", "error"], "");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr(&output).contains("error[Q0904]"));
+
+    let output = quark_run(
+        "json",
+        src,
+        &["--diagnostics-format", "json", "--error-limit", "5"],
+        "",
+    );
+    // a warning does not stop the program, whose status is what main returns
+    assert_eq!(output.status.code(), Some(2));
+    assert!(stderr(&output).starts_with("[\n{\"code\":\"Q0904\""));
+}
+
+#[test]
+fn documents_do_not_mix_with_program_output() {
+    let src = "int main() { int x = 2.5; putint(x); return 0; }";
+
+    for format in ["json", "sarif"] {
+        let output = quark_run(format, src, &["--diagnostics-format", format], "");
+
+        assert_eq!(output.status.code(), Some(0));
+        assert_eq!(stdout(&output), "2");
+        assert!(stderr(&output).contains("Q0904"), "{}", stderr(&output));
+        assert!(stderr(&output).trim_end().ends_with(['}', ']']));
+    }
+
+    let output = quark_run(
+        "no-main",
+        "int f() { return 1; }",
+        &["--diagnostics-format", "json"],
+        "",
+    );
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(stdout(&output), "");
+    assert!(stderr(&output).starts_with("[\n{"), "{}", stderr(&output));
+    assert!(stderr(&output).contains("no main function"));
+}